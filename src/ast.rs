@@ -17,6 +17,10 @@ pub struct Block {
 #[derive(Debug, Clone, Copy)]
 pub struct Proc {
     pub symbol: u32,
+    // Each of these is an ExprKind::Param
+    pub params: ExprRange,
+    // ExprId::NULL when the procedure doesn't return anything
+    pub return_type: ExprId,
     pub code: ExprId,
 }
 
@@ -29,29 +33,108 @@ pub struct Expr {
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum BinaryExprKind {
     Add,
+    Subtract,
     Multiply,
+    Divide,
+    Modulo,
+
+    ShiftLeft,
+    ShiftRight,
+    BitAnd,
+    BitOr,
+    BitXor,
+
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
     Equal,
+    NotEqual,
+
+    // These short-circuit
+    BoolAnd,
+    BoolOr,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum UnaryExprKind {
+    Negate,  // -x
+    BoolNot, // !x
+    BitNot,  // ~x
+    Ref,     // &x
+    Deref,   // *x
 }
 
 #[derive(Debug, Clone, Copy)]
 pub enum ExprKind {
-    Integer(u64),
+    // suffix is the type from a suffix like `10u8`, or ExprId::NULL
+    Integer {
+        value: u64,
+        suffix: ExprId,
+    },
+    // suffix is the type from a suffix like `1.5f32`, or ExprId::NULL
+    Float {
+        value: f64,
+        suffix: ExprId,
+    },
+    Bool(bool),
+    // Unicode code point of a char literal like 'a'
+    Char(u32),
+    // symbol is the text of the string with escapes already decoded
+    String {
+        symbol: u32,
+    },
     Ident {
         symbol: u32,
     },
+    // `[a, b, c]`
+    Array {
+        elems: ExprRange,
+    },
+    // `Point{x: 1, y: 2}`; each field is an ExprKind::FieldValue
+    StructLiteral {
+        symbol: u32,
+        fields: ExprRange,
+    },
+    FieldValue {
+        symbol: u32,
+        value: ExprId,
+    },
 
     Procedure(Proc),
+    Param {
+        symbol: u32,
+        ty: ExprId,
+    },
+    // value is ExprId::NULL for a bare `return`
+    Return {
+        value: ExprId,
+    },
 
     Call {
         callee: ExprId,
         args: ExprRange,
     },
 
+    UnaryOp {
+        kind: UnaryExprKind,
+        value: ExprId,
+    },
     BinaryOp {
         kind: BinaryExprKind,
         left: ExprId,
         right: ExprId,
     },
+    // `a.field`
+    Field {
+        value: ExprId,
+        symbol: u32,
+    },
+    // `a[i]`, or `a[i..j]` when index is an ExprKind::Range
+    Index {
+        value: ExprId,
+        index: ExprId,
+    },
 
     // TODO Eventually support:
     //
@@ -59,13 +142,56 @@ pub enum ExprKind {
     // let a = 1
     // let a : int
     // let a
+    // ty is ExprId::NULL when there's no type annotation
     Let {
         symbol: u32,
+        ty: ExprId,
         value: ExprId,
     },
 
-    Assign {
+    // `type Name struct { ... }`; ty is an ExprKind::StructType or an
+    // ExprKind::EnumType
+    TypeDecl {
         symbol: u32,
+        ty: ExprId,
+    },
+
+    // `*T`
+    PointerType {
+        pointee: ExprId,
+    },
+    // `[N]T`
+    ArrayType {
+        len: ExprId,
+        elem: ExprId,
+    },
+    // `[]T`
+    SliceType {
+        elem: ExprId,
+    },
+    // `enum { Red, Green }`; each variant is an ExprKind::Variant
+    EnumType {
+        variants: ExprRange,
+    },
+    // `Circle(f64)`; payload is the types of the values in the variant, or
+    // ExprRange::EMPTY
+    Variant {
+        symbol: u32,
+        payload: ExprRange,
+    },
+    // `struct { x: s64, y: s64 }`; each field is an ExprKind::Param
+    StructType {
+        fields: ExprRange,
+    },
+
+    Assign {
+        target: ExprId,
+        value: ExprId,
+    },
+    // `a += 1`; the target only gets evaluated once
+    CompoundAssign {
+        kind: BinaryExprKind,
+        target: ExprId,
         value: ExprId,
     },
 
@@ -81,9 +207,54 @@ pub enum ExprKind {
         if_false: ExprId,
     },
 
+    // Loop labels are ExprKind::Ident, or ExprId::NULL for unlabeled
+    // loops/jumps
     ForInfinite {
+        label: ExprId,
         body: ExprId,
     },
+    ForCond {
+        label: ExprId,
+        cond: ExprId,
+        body: ExprId,
+    },
+    // range is an ExprKind::Range
+    ForRange {
+        label: ExprId,
+        symbol: u32,
+        range: ExprId,
+        body: ExprId,
+    },
+    Break {
+        label: ExprId,
+    },
+    Continue {
+        label: ExprId,
+    },
+
+    // Runs `value` when the enclosing block exits
+    Defer {
+        value: ExprId,
+    },
+
+    // Each arm is an ExprKind::MatchArm
+    Match {
+        value: ExprId,
+        arms: ExprRange,
+    },
+    // Patterns are ExprKind::Ident for names and `_` (which uses the
+    // Underscore keyword), ExprKind::Field for variants like `Color.Red`, and
+    // ExprKind::Call for variants with payloads like `Shape.Circle(r)`
+    MatchArm {
+        pattern: ExprId,
+        body: ExprId,
+    },
+
+    // a..b; only valid in specific places, like for loops
+    Range {
+        start: ExprId,
+        end: ExprId,
+    },
 }
 
 impl ExprKind {
@@ -91,17 +262,45 @@ impl ExprKind {
         use ExprKind::*;
 
         return match self {
-            Integer(v) => "Integer",
+            Integer { .. } => "Integer",
+            Float { .. } => "Float",
+            Bool(v) => "Bool",
+            Char(c) => "Char",
+            String { .. } => "String",
             Ident { .. } => "Ident",
+            Array { .. } => "Array",
+            StructLiteral { .. } => "StructLiteral",
+            FieldValue { .. } => "FieldValue",
             Procedure(p) => "Procedure",
+            Param { .. } => "Param",
+            Return { .. } => "Return",
             Call { .. } => "Call",
+            UnaryOp { .. } => "UnaryOp",
             BinaryOp { .. } => "BinaryOp",
+            Field { .. } => "Field",
+            Index { .. } => "Index",
             Let { .. } => "Let",
+            TypeDecl { .. } => "TypeDecl",
+            PointerType { .. } => "PointerType",
+            ArrayType { .. } => "ArrayType",
+            SliceType { .. } => "SliceType",
+            StructType { .. } => "StructType",
+            EnumType { .. } => "EnumType",
+            Variant { .. } => "Variant",
             Assign { .. } => "Assign",
+            CompoundAssign { .. } => "CompoundAssign",
             Block(b) => "Block",
             If { .. } => "If",
             IfElse { .. } => "IfElse",
             ForInfinite { .. } => "ForInfinite",
+            ForCond { .. } => "ForCond",
+            ForRange { .. } => "ForRange",
+            Break { .. } => "Break",
+            Continue { .. } => "Continue",
+            Defer { .. } => "Defer",
+            Match { .. } => "Match",
+            MatchArm { .. } => "MatchArm",
+            Range { .. } => "Range",
         };
     }
 }
@@ -360,7 +559,7 @@ impl AstAlloc {
             let exprs = core::slice::from_raw_parts_mut(exprs, len);
             let locs = core::slice::from_raw_parts_mut(locs, len);
 
-            for (i, expr) in spanned_exprs.into_iter().enumerate() {
+            for (i, expr) in spanned_exprs.iter().enumerate() {
                 exprs[i] = expr.kind;
                 locs[i].start = expr.loc.start;
                 locs[i].end = expr.loc.end;
@@ -378,7 +577,10 @@ fn ast() {
 
         for i in 0u32..64 {
             let id = ast_alloc.make(Expr {
-                kind: ExprKind::Integer(i as u64),
+                kind: ExprKind::Integer {
+                    value: i as u64,
+                    suffix: ExprId::NULL,
+                },
                 loc: CodeLoc {
                     start: i,
                     end: i + 1,
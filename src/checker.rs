@@ -37,16 +37,28 @@ pub enum ValueLocation {
     NoValue,
 }
 
+// Register IDs handed out by the checker need to stay below this; registers 29
+// through 31 are used by the assembler as scratch space, and register 0 is
+// reserved for procedure calls
+const REGISTER_ID_LIMIT: u16 = 29;
+
+// Once this many registers are in use, the left side of a binary operation
+// gets saved on the stack while the right side is checked
+const SPILL_REGISTER_ID: u16 = 16;
+
 const NULL: Value = Value {
     op: Operand::Null,
     ty: Type::Null,
 };
 
-pub fn check_ast(ast: &Ast) -> Result<(Graph, u32), Error> {
-    let mut types = TypeEnv {};
+pub fn check_ast(table: &StringTable, ast: &Ast) -> Result<(Graph, u32), Error> {
+    let mut types = TypeEnv::new();
 
     let mut scope = ScopeEnv {
         vars: HashMap::new(),
+        procs: HashMap::new(),
+        types: HashMap::new(),
+        defers: Vec::new(),
         kind: ScopeKind::Global {},
     };
 
@@ -60,6 +72,7 @@ pub fn check_ast(ast: &Ast) -> Result<(Graph, u32), Error> {
     };
 
     let mut env = CheckEnv {
+        table,
         types: &mut types,
         graph: &mut graph,
         ids: &mut ids,
@@ -67,8 +80,9 @@ pub fn check_ast(ast: &Ast) -> Result<(Graph, u32), Error> {
         scope,
     };
 
-    for expr in ast.block.stmts {
-        env.check_expr(ValueSlot::Delete, expr)?;
+    let value = env.check_block(ValueSlot::Delete, &ast.block, Type::Null)?;
+    if value.ty != Type::Never {
+        env.emit_defers(1)?;
     }
 
     core::mem::drop(env);
@@ -84,14 +98,248 @@ pub fn check_ast(ast: &Ast) -> Result<(Graph, u32), Error> {
     return Ok((graph, entry));
 }
 
-pub struct TypeEnv {}
+// Types that are built out of other types, like pointers, get their info
+// stored here, and refer to it by index
+pub struct TypeEnv {
+    infos: Vec<TypeInfo>,
+    ids: HashMap<TypeInfo, u16>,
+
+    // Structs with the same fields are still different types, so these don't
+    // get deduplicated like the infos do. Same goes for enums.
+    structs: Vec<StructInfo>,
+    enums: Vec<EnumInfo>,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum TypeInfo {
+    Pointer { pointee: Type },
+    Array { elem: Type, len: u32 },
+    Slice { elem: Type },
+}
+
+pub struct StructInfo {
+    // The ExprKind::TypeDecl that declared this struct
+    decl: ExprId,
+    layout: Layout,
+    fields: Vec<FieldInfo>,
+    size: u32,
+    align: u32,
+}
+
+pub struct EnumInfo {
+    // The ExprKind::TypeDecl that declared this enum
+    decl: ExprId,
+    layout: Layout,
+    variants: Vec<VariantInfo>,
+    size: u32,
+    align: u32,
+}
+
+pub struct VariantInfo {
+    symbol: u32,
+
+    // Type and offset of each value in the payload
+    payload: Vec<(Type, u32)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FieldInfo {
+    symbol: u32,
+    ty: Type,
+    offset: u32,
+}
+
+// Struct layouts get computed on demand, since fields can use structs that
+// are declared later on
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Layout {
+    NotStarted,
+    InProgress,
+    Done,
+}
+
+impl TypeEnv {
+    fn new() -> Self {
+        return Self {
+            infos: Vec::new(),
+            ids: HashMap::new(),
+            structs: Vec::new(),
+            enums: Vec::new(),
+        };
+    }
+
+    // Equal infos always get the same ID, so types can be compared directly
+    fn add(&mut self, info: TypeInfo) -> u16 {
+        if let Some(&id) = self.ids.get(&info) {
+            return id;
+        }
+
+        let id = expect(u16::try_from(self.infos.len()));
+        self.infos.push(info);
+        self.ids.insert(info, id);
+
+        return id;
+    }
+
+    pub fn pointer_to(&mut self, pointee: Type) -> Type {
+        return Type::Pointer(self.add(TypeInfo::Pointer { pointee }));
+    }
+
+    pub fn pointee(&self, ty: Type) -> Option<Type> {
+        let id = match ty {
+            Type::Pointer(id) => id,
+            _ => return None,
+        };
+
+        return match self.infos[id as usize] {
+            TypeInfo::Pointer { pointee } => Some(pointee),
+            _ => None,
+        };
+    }
+
+    pub fn array_of(&mut self, elem: Type, len: u32) -> Type {
+        return Type::Array(self.add(TypeInfo::Array { elem, len }));
+    }
+
+    pub fn slice_of(&mut self, elem: Type) -> Type {
+        return Type::Slice(self.add(TypeInfo::Slice { elem }));
+    }
+
+    // The element type of an array or slice, along with the length for arrays
+    pub fn elem(&self, ty: Type) -> Option<(Type, Option<u32>)> {
+        let id = match ty {
+            Type::Array(id) | Type::Slice(id) => id,
+            _ => return None,
+        };
+
+        return match self.infos[id as usize] {
+            TypeInfo::Array { elem, len } => Some((elem, Some(len))),
+            TypeInfo::Slice { elem } => Some((elem, None)),
+            _ => None,
+        };
+    }
+
+    fn add_struct(&mut self, decl: ExprId) -> Type {
+        let id = expect(u16::try_from(self.structs.len()));
+        self.structs.push(StructInfo {
+            decl,
+            layout: Layout::NotStarted,
+            fields: Vec::new(),
+            size: 0,
+            align: 1,
+        });
+
+        return Type::Struct(id);
+    }
+
+    // Enums without payloads are just their variant index, so their layout
+    // is already known
+    fn add_enum(&mut self, decl: ExprId, symbols: Vec<u32>, has_payload: bool) -> Type {
+        let id = expect(u16::try_from(self.enums.len()));
+
+        let variants = symbols.into_iter().map(|symbol| VariantInfo {
+            symbol,
+            payload: Vec::new(),
+        });
+
+        self.enums.push(EnumInfo {
+            decl,
+            layout: match has_payload {
+                true => Layout::NotStarted,
+                false => Layout::Done,
+            },
+            variants: variants.collect(),
+            size: 4,
+            align: 4,
+        });
+
+        return match has_payload {
+            true => Type::Union(id),
+            false => Type::Enum(id),
+        };
+    }
+
+    pub fn variants(&self, ty: Type) -> Option<&[VariantInfo]> {
+        return match ty {
+            Type::Enum(id) | Type::Union(id) => Some(&self.enums[id as usize].variants),
+            _ => None,
+        };
+    }
+
+    pub fn fields(&self, ty: Type) -> Option<&[FieldInfo]> {
+        return match ty {
+            Type::Struct(id) => Some(&self.structs[id as usize].fields),
+            _ => None,
+        };
+    }
+
+    // Size in bytes of a value of this type when it's in memory; slices are a
+    // pointer and then a u64 length
+    pub fn size_of(&self, ty: Type) -> u32 {
+        if let Type::Struct(id) = ty {
+            return self.structs[id as usize].size;
+        }
+
+        if let Type::Union(id) = ty {
+            return self.enums[id as usize].size;
+        }
+
+        return match self.elem(ty) {
+            Some((elem, Some(len))) => self.size_of(elem) * len,
+            Some((_, None)) => 16,
+            None => ty.size(),
+        };
+    }
+
+    // Values that fit in a register are aligned to their size, and everything
+    // else is aligned like the most aligned thing in it
+    pub fn align_of(&self, ty: Type) -> u32 {
+        if let Type::Struct(id) = ty {
+            return self.structs[id as usize].align;
+        }
+
+        if let Type::Union(id) = ty {
+            return self.enums[id as usize].align;
+        }
+
+        return match self.elem(ty) {
+            Some((elem, Some(_))) => self.align_of(elem),
+            Some((_, None)) => 8,
+            None => ty.size(),
+        };
+    }
+
+    // Stack variables that fit in a register always get 8 bytes
+    fn stack_size(&self, ty: Type) -> u16 {
+        if ty.is_aggregate() {
+            return self.size_of(ty) as u16;
+        }
+
+        return 8;
+    }
+}
 
 struct GraphAppend {
     block_id: u32,
     ops: Pod<GraphOp>,
 }
 
+// The value of a block, if-else or match gets copied here when it's an
+// array, slice, struct or enum with a payload that was made in the stack
+// space that the expression cleans up. The slot is declared before anything
+// in the expression, so that it outlives that stack space, but its size
+// isn't known until the expression's type is.
+#[derive(Clone, Copy)]
+struct ResultSlot {
+    id: u16,
+    // Where the slot's DeclareStack op is, so that its size can be filled in
+    block_id: u32,
+    index: usize,
+}
+
 struct CheckEnv<'a> {
+    // Only used for the names of things in error messages
+    table: &'a StringTable,
     types: &'a mut TypeEnv,
     graph: &'a mut Graph,
     ids: &'a mut IdTracker,
@@ -100,28 +348,144 @@ struct CheckEnv<'a> {
 }
 
 impl<'a> CheckEnv<'a> {
-    fn check_block(&mut self, slot: ValueSlot, block: &Block) -> Result<Value, Error> {
+    fn check_block(
+        &mut self,
+        slot: ValueSlot,
+        block: &Block,
+        expected: Type,
+    ) -> Result<Value, Error> {
         use ExprKind::*;
 
+        // Types are declared before anything else, so that everything in the
+        // block can use them. Their layouts come after all of them are
+        // declared, since fields can use types from later in the block.
+        for expr in block.stmts {
+            let (symbol, ty) = match *expr {
+                TypeDecl { symbol, ty } => (symbol, ty),
+                _ => continue,
+            };
+
+            let ty = match *ty {
+                EnumType { variants } => self.check_enum_type(expr, variants)?,
+                _ => self.types.add_struct(expr),
+            };
+
+            self.declare_type(expr, symbol, ty)?;
+        }
+
+        for expr in block.stmts {
+            if let TypeDecl { symbol, .. } = *expr {
+                let ty = self.scope.types[&symbol];
+                self.require_layout(expr, ty)?;
+            }
+        }
+
+        // Procedures are declared next, before the rest of the block gets
+        // checked, so that they can be called before their declaration, and
+        // so that they can call each other.
         for expr in block.stmts {
             let p = match *expr {
                 Procedure(p) => p,
                 _ => continue,
             };
 
-            unimplemented!("procedures aren't implemented yet");
+            let return_type = match p.return_type.is_null() {
+                true => Type::Null,
+                false => self.check_type(p.return_type)?,
+            };
+
+            let info = ProcInfo {
+                block_id: self.graph.get_block_id(),
+                params: p.params,
+                return_type,
+            };
+
+            self.declare_proc(expr, p.symbol, info)?;
         }
 
-        for expr in block.stmts {
-            self.check_expr(ValueSlot::Delete, expr)?;
+        let mut value = NULL;
+        let mut never_returns = false;
+
+        let count = block.stmts.len();
+        for (index, expr) in block.stmts.into_iter().enumerate() {
+            // The last statement in the block is the value of the block, so its
+            // registers need to stay alive
+            if index + 1 == count {
+                value = match expected {
+                    Type::Null => self.check_expr(slot, expr)?,
+                    _ => self.check_expr_as(expr, expected)?,
+                };
+            } else if self.check_stmt(expr)? == Type::Never {
+                never_returns = true;
+            }
         }
 
-        return Ok(NULL);
+        if never_returns {
+            value.ty = Type::Never;
+        }
+
+        return Ok(value);
+    }
+
+    // Registers only need to live as long as the statement that made them, so
+    // the register numbering gets reset after every statement. The same goes
+    // for temporary stack space, like for array literals, except when the
+    // statement is a `let` that might still be using it.
+    fn check_stmt(&mut self, id: ExprId) -> Result<Type, Error> {
+        let next_op_id = self.ids.next_op_id;
+        let var_base = self.ids.next_variable_id;
+
+        let value = self.check_expr(ValueSlot::Delete, id)?;
+
+        self.ids.next_op_id = next_op_id;
+
+        if !matches!(*id, ExprKind::Let { .. }) {
+            self.free_temps(var_base, id);
+        }
+
+        return Ok(value.ty);
+    }
+
+    // Checks each item with `check`, which stores or prints it somewhere.
+    // Items are done with once that happens, so their registers can get
+    // reused by the next one.
+    fn check_each_reusing_regs<T>(
+        &mut self,
+        items: impl IntoIterator<Item = T>,
+        mut check: impl FnMut(&mut Self, T) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let next_op_id = self.ids.next_op_id;
+
+        for item in items {
+            check(self, item)?;
+
+            self.ids.next_op_id = next_op_id;
+        }
+
+        return Ok(());
+    }
+
+    // Deallocates the stack variables made since `var_base`
+    fn free_temps(&mut self, var_base: u16, id: ExprId) {
+        let count = self.ids.next_variable_id - var_base;
+        if count == 0 {
+            return;
+        }
+
+        self.ids.next_variable_id = var_base;
+
+        let kind = GraphOpKind::StackDealloc { count };
+        let op = GraphOp::new(kind, Type::Null, id);
+        self.append.ops.push(op);
     }
 
     fn check_expr(&mut self, slot: ValueSlot, id: ExprId) -> Result<Value, Error> {
         let value = self.check_expr_inner(slot, id)?;
 
+        if self.ids.next_op_id > REGISTER_ID_LIMIT {
+            return Err(Error::new("expression is too complex", id.loc()));
+        }
+
         return Ok(value);
     }
 
@@ -132,46 +496,278 @@ impl<'a> CheckEnv<'a> {
 
         match *expr {
             Procedure(p) => {
+                let info = match self.scope.procs.get(&p.symbol) {
+                    Some(&info) => info,
+                    None => panic!("procedure wasn't declared before being checked"),
+                };
+
                 let mut ids = IdTracker::new();
-                let mut proc_child = self.chain_proc(&mut ids);
+                let mut append = GraphAppend {
+                    block_id: info.block_id,
+                    ops: Pod::new(),
+                };
+
+                let mut proc_child = self.chain_proc(&mut ids, &mut append, info.return_type);
+
+                // Arguments are already on the stack when the procedure starts,
+                // so they don't need a DeclareStack
+                for param in p.params {
+                    let (symbol, ty) = match *param {
+                        Param { symbol, ty } => (symbol, ty),
+                        _ => unreachable!(),
+                    };
+
+                    let ty = proc_child.check_type(ty)?;
+                    let var_id = proc_child.reserve_var_id();
+                    let info = VariableInfo { id: var_id, ty };
+
+                    proc_child.declare(param, symbol, info)?;
+                }
 
                 let result = proc_child.check_expr(ValueSlot::Delete, p.code)?;
 
+                if result.ty != Type::Never {
+                    if info.return_type != Type::Null {
+                        return Err(Error::new(
+                            "procedure needs to return a value at the end",
+                            p.code.loc(),
+                        ));
+                    }
+
+                    let kind = GraphOpKind::Return {
+                        value: Operand::Null,
+                    };
+                    let op = GraphOp::new(kind, Type::Null, id);
+                    proc_child.append.ops.push(op);
+                }
+
+                core::mem::drop(proc_child);
+
+                self.graph.write_block(append.block_id, append.ops);
+
                 return Ok(NULL);
             }
 
-            Integer(value) => {
+            Return { value } => {
+                let return_type = match self.return_type() {
+                    Some(ty) => ty,
+                    None => {
+                        return Err(Error::new("return outside of a procedure", id.loc()));
+                    }
+                };
+
+                let mut result = match value.is_null() {
+                    true => NULL,
+                    false => self.check_expr_as(value, return_type)?,
+                };
+
+                if result.ty != return_type {
+                    return Err(Error::new(
+                        "returned value doesn't match the procedure's return type",
+                        id.loc(),
+                    ));
+                }
+
+                // Large values get copied to wherever the caller said to put
+                // them, which was passed in register 0. This happens before
+                // the defers run, since they might change the value.
+                if result.ty.is_aggregate() {
+                    let pointer = Operand::RegisterValue { id: 0 };
+                    self.store(value, pointer, result);
+
+                    result.op = pointer;
+                }
+
+                let depth = self.proc_depth();
+                self.emit_defers(depth)?;
+
+                let kind = GraphOpKind::Return { value: result.op };
+                let op = GraphOp::new(kind, result.ty, id);
+                self.append.ops.push(op);
+
+                return Ok(Value::new(Operand::Null, Type::Never));
+            }
+
+            Bool(value) => {
                 let target = self.register_id();
 
-                let kind = GraphOpKind::ConstantU64 { target, value };
-                let op = GraphOp::new(kind, Type::U64, id);
+                let kind = GraphOpKind::ConstantU64 {
+                    target,
+                    value: value as u64,
+                };
+                let op = GraphOp::new(kind, Type::Bool, id);
                 self.append.ops.push(op);
 
-                return Ok(Value::new(target, Type::U64));
+                return Ok(Value::new(target, Type::Bool));
             }
 
-            Let { symbol, value } => {
-                // TODO fix in a sec
-                let var_id = self.reserve_var_id();
-                let slot = ValueSlot::StackLocation { id: var_id };
+            Integer { .. } | Float { .. } => {
+                return self.check_expr_as(id, Type::Null);
+            }
+
+            Char(c) => {
+                let target = self.register_id();
+
+                let kind = GraphOpKind::ConstantU64 {
+                    target,
+                    value: c as u64,
+                };
+                let op = GraphOp::new(kind, Type::Char, id);
+                self.append.ops.push(op);
+
+                return Ok(Value::new(target, Type::Char));
+            }
+
+            String { symbol } => {
+                let target = self.register_id();
+
+                let kind = GraphOpKind::ConstantString { target, symbol };
+                let op = GraphOp::new(kind, Type::String, id);
+                self.append.ops.push(op);
+
+                return Ok(Value::new(target, Type::String));
+            }
+
+            Assign { target, value } => {
+                let symbol = match *target {
+                    Ident { symbol } => symbol,
+
+                    UnaryOp {
+                        kind: UnaryExprKind::Deref,
+                        value: pointer,
+                    } => {
+                        let (pointer, pointee) = self.check_deref(pointer)?;
 
-                let result = self.check_expr(slot, value)?;
+                        let result = self.check_expr_as(value, pointee)?;
+                        if result.ty != pointee {
+                            return Err(Error::new(
+                                "assigned value doesn't match the pointer's type",
+                                value.loc(),
+                            ));
+                        }
+
+                        self.store(id, pointer.op, result);
+
+                        return Ok(NULL);
+                    }
+
+                    Field { .. } => {
+                        let (pointer, field_ty) = self.check_field(target)?;
+
+                        let result = self.check_expr_as(value, field_ty)?;
+                        if result.ty != field_ty {
+                            return Err(Error::new(
+                                "assigned value doesn't match the field's type",
+                                value.loc(),
+                            ));
+                        }
+
+                        self.store(id, pointer, result);
+
+                        return Ok(NULL);
+                    }
+
+                    Index {
+                        value: array,
+                        index,
+                    } if !matches!(*index, Range { .. }) => {
+                        let (pointer, elem) = self.check_index(array, index)?;
+
+                        let result = self.check_expr_as(value, elem)?;
+                        if result.ty != elem {
+                            return Err(Error::new(
+                                "assigned value doesn't match the element type",
+                                value.loc(),
+                            ));
+                        }
+
+                        self.store(id, pointer, result);
+
+                        return Ok(NULL);
+                    }
+
+                    _ => {
+                        return Err(Error::new("can't assign to this", target.loc()));
+                    }
+                };
+
+                let var_info = match self.search(symbol) {
+                    Some(e) => e,
+                    None => {
+                        return Err(Error::new("couldn't find variable", target.loc()));
+                    }
+                };
+
+                let result = self.check_expr_as(value, var_info.ty)?;
+                if result.ty != var_info.ty {
+                    return Err(Error::new(
+                        "assigned value doesn't match the variable's type",
+                        value.loc(),
+                    ));
+                }
+
+                self.write_var(id, var_info, result);
+
+                return Ok(NULL);
+            }
+
+            CompoundAssign {
+                kind,
+                target,
+                value,
+            } => {
+                let (pointer, ty) = self.check_target(target)?;
+
+                let base = self.ids.next_op_id;
+                let current = self.load(target, pointer, ty);
+                let right = self.check_expr_as(value, ty)?;
+                let result = self.check_binary_op(id, kind, base, current, right)?;
+
+                if result.ty != ty {
+                    return Err(Error::new(
+                        "assigned value doesn't match the target's type",
+                        value.loc(),
+                    ));
+                }
+
+                self.store(id, pointer, result);
+
+                return Ok(NULL);
+            }
+
+            Let { symbol, ty, value } => {
+                let result = match ty.is_null() {
+                    true => self.check_expr(ValueSlot::SaveSomewhere, value)?,
+                    false => {
+                        let ty = self.check_type(ty)?;
+                        let result = self.check_expr_as(value, ty)?;
+
+                        if result.ty != ty {
+                            return Err(Error::new(
+                                "value doesn't match the variable's declared type",
+                                value.loc(),
+                            ));
+                        }
+
+                        result
+                    }
+                };
+
+                // The variable ID has to be reserved after the value is checked,
+                // so that stack IDs line up with the order of the DeclareStack ops
+                let var_id = self.reserve_var_id();
                 let info = VariableInfo {
                     id: var_id,
                     ty: result.ty,
                 };
                 self.declare(id, symbol, info)?;
 
-                let kind = GraphOpKind::DeclareStack { size: 8 };
-                let op = GraphOp::new(kind, Type::U64, value);
+                let size = self.types.stack_size(result.ty);
+                let kind = GraphOpKind::DeclareStack { size };
+                let op = GraphOp::new(kind, result.ty, value);
                 self.append.ops.push(op);
 
-                let kind = GraphOpKind::Mov {
-                    target: Operand::Null,
-                    source: result.op,
-                };
-                let op = GraphOp::new(kind, Type::U64, id);
-                self.append.ops.push(op);
+                self.write_var(id, info, result);
 
                 return Ok(NULL);
             }
@@ -184,6 +780,12 @@ impl<'a> CheckEnv<'a> {
                     }
                 };
 
+                if var_info.ty.is_aggregate() {
+                    let target = self.var_address(id, var_info);
+
+                    return Ok(Value::new(target, var_info.ty));
+                }
+
                 let target = self.register_id();
 
                 let kind = GraphOpKind::Mov {
@@ -197,139 +799,2834 @@ impl<'a> CheckEnv<'a> {
             }
 
             If { cond, if_true } => {
-                let end_block = self.graph.get_block_id();
+                let cond = self.check_cond(cond)?;
 
-                // let var_id = self.reserve_var_id();
+                let if_true_block = self.graph.get_block_id();
+                let end_block = self.graph.get_block_id();
 
-                // let if_true_block_id = self.graph.get_block_id();
-                // let if_true_arm = Arm {
-                //     block_id: if_true_block_id,
-                //     expr: if_true,
-                // };
+                let kind = GraphOpKind::Branch {
+                    cond: cond.op,
+                    if_true: if_true_block,
+                    if_false: end_block,
+                };
+                let op = GraphOp::new(kind, Type::Null, id);
+                self.append.ops.push(op);
 
-                // let value = self.check_arms(var_id, end_block, &[if_true_arm])?;
+                let if_true_arm = Arm {
+                    block_id: if_true_block,
+                    expr: if_true,
+                    pattern: ExprId::NULL,
+                    value: NULL,
+                };
 
-                // assert_eq!(self.graph.ops.len(), 0);
-                // let ops = core::mem::replace(&mut self.graph.ops, Pod::new());
-                // self.graph.graph.write_block(self.graph.block_id, ops);
-                // self.graph.block_id = end_block;
+                // Without an else, there's no value to produce
+                self.check_arms(end_block, &[if_true_arm], Type::Null, None)?;
 
-                // return Ok(value);
                 return Ok(NULL);
             }
 
-            IfElse {
-                cond,
-                if_true,
-                if_false,
-            } => {
+            IfElse { .. } => {
+                return self.check_if_else(slot, id, Type::Null);
+            }
+
+            ForInfinite { label, body } => {
+                let body_block = self.graph.get_block_id();
+                let end_block = self.graph.get_block_id();
+
+                let kind = GraphOpKind::Jump { block: body_block };
+                let op = GraphOp::new(kind, Type::Null, id);
+                self.append.ops.push(op);
+
+                self.replace_block(GraphAppend {
+                    block_id: body_block,
+                    ops: Pod::new(),
+                });
+
+                let broken = self.check_loop_body(label, body, body_block, end_block)?;
+
+                self.replace_block(GraphAppend {
+                    block_id: end_block,
+                    ops: Pod::new(),
+                });
+
+                // Without a break, the only ways out are return and crashing
+                return match broken {
+                    true => Ok(NULL),
+                    false => Ok(Value::new(Operand::Null, Type::Never)),
+                };
+            }
+
+            ForCond { label, cond, body } => {
+                let cond_block = self.graph.get_block_id();
+                let body_block = self.graph.get_block_id();
                 let end_block = self.graph.get_block_id();
 
-                // let value = self.check_arms(end_block, &[if_true, if_false])?;
+                let kind = GraphOpKind::Jump { block: cond_block };
+                let op = GraphOp::new(kind, Type::Null, id);
+                self.append.ops.push(op);
+
+                self.replace_block(GraphAppend {
+                    block_id: cond_block,
+                    ops: Pod::new(),
+                });
+
+                let cond = self.check_cond(cond)?;
+
+                let kind = GraphOpKind::Branch {
+                    cond: cond.op,
+                    if_true: body_block,
+                    if_false: end_block,
+                };
+                let op = GraphOp::new(kind, Type::Null, id);
+                self.append.ops.push(op);
+
+                self.replace_block(GraphAppend {
+                    block_id: body_block,
+                    ops: Pod::new(),
+                });
 
-                // assert_eq!(self.graph.ops.len(), 0);
-                // let ops = core::mem::replace(&mut self.graph.ops, Pod::new());
-                // self.graph.graph.write_block(self.graph.block_id, ops);
-                // self.graph.block_id = end_block;
+                self.check_loop_body(label, body, cond_block, end_block)?;
+
+                self.replace_block(GraphAppend {
+                    block_id: end_block,
+                    ops: Pod::new(),
+                });
 
                 return Ok(NULL);
             }
 
-            BinaryOp { kind, left, right } => {
-                let left_value = self.check_expr(ValueSlot::SaveSomewhere, left)?;
-                let right_value = self.check_expr(ValueSlot::SaveSomewhere, right)?;
+            ForRange {
+                label,
+                symbol,
+                range,
+                body,
+            } => {
+                let (start, end) = match *range {
+                    Range { start, end } => (start, end),
+                    _ => unreachable!(),
+                };
 
-                if left_value.ty != right_value.ty {
+                // The end value stays in a register for the whole loop
+                let var_base = self.ids.next_variable_id;
+                let (start_value, end_value) = self.check_operands(start, end)?;
+                self.free_temps(var_base, range);
+
+                let ty = start_value.ty;
+                if !ty.is_integer() {
+                    return Err(Error::new("range bounds should be integers", start.loc()));
+                }
+
+                if end_value.ty != ty {
                     return Err(Error::new(
-                        "binary operation should be on values of similar type",
-                        id.loc(),
+                        "range bounds should have the same type",
+                        range.loc(),
                     ));
                 }
 
-                let target = self.register_id();
+                // The loop variable gets its own scope, which wraps the loop
+                let mut outer = self.chain_local();
+
+                let var_id = outer.reserve_var_id();
+                let info = VariableInfo { id: var_id, ty };
+                outer.declare(id, symbol, info)?;
+
+                let counter = Operand::StackLocal { id: var_id };
+
+                let kind = GraphOpKind::DeclareStack { size: 8 };
+                let op = GraphOp::new(kind, ty, id);
+                outer.append.ops.push(op);
+
+                let kind = GraphOpKind::Mov {
+                    target: counter,
+                    source: start_value.op,
+                };
+                let op = GraphOp::new(kind, ty, start);
+                outer.append.ops.push(op);
+
+                let cond_block = outer.graph.get_block_id();
+                let body_block = outer.graph.get_block_id();
+                let step_block = outer.graph.get_block_id();
+                let end_block = outer.graph.get_block_id();
+
+                let kind = GraphOpKind::Jump { block: cond_block };
+                let op = GraphOp::new(kind, Type::Null, id);
+                outer.append.ops.push(op);
+
+                outer.replace_block(GraphAppend {
+                    block_id: cond_block,
+                    ops: Pod::new(),
+                });
+
+                let current = outer.register_id();
+                let kind = GraphOpKind::Mov {
+                    target: current,
+                    source: counter,
+                };
+                let op = GraphOp::new(kind, ty, range);
+                outer.append.ops.push(op);
+
+                let cond = outer.register_id();
+                let kind = GraphOpKind::CompLt {
+                    target: cond,
+                    left: current,
+                    right: end_value.op,
+                };
+                let op = GraphOp::new(kind, ty, range);
+                outer.append.ops.push(op);
+
+                let kind = GraphOpKind::Branch {
+                    cond,
+                    if_true: body_block,
+                    if_false: end_block,
+                };
+                let op = GraphOp::new(kind, Type::Null, range);
+                outer.append.ops.push(op);
+
+                outer.replace_block(GraphAppend {
+                    block_id: body_block,
+                    ops: Pod::new(),
+                });
+
+                outer.check_loop_body(label, body, step_block, end_block)?;
+
+                outer.replace_block(GraphAppend {
+                    block_id: step_block,
+                    ops: Pod::new(),
+                });
+
+                let kind = GraphOpKind::Mov {
+                    target: current,
+                    source: counter,
+                };
+                let op = GraphOp::new(kind, ty, range);
+                outer.append.ops.push(op);
+
+                let one = outer.register_id();
+                let kind = GraphOpKind::ConstantU64 {
+                    target: one,
+                    value: 1,
+                };
+                let op = GraphOp::new(kind, ty, range);
+                outer.append.ops.push(op);
+
+                let kind = GraphOpKind::Add {
+                    target: counter,
+                    left: current,
+                    right: one,
+                };
+                let op = GraphOp::new(kind, ty, range);
+                outer.append.ops.push(op);
+
+                let kind = GraphOpKind::Jump { block: cond_block };
+                let op = GraphOp::new(kind, Type::Null, range);
+                outer.append.ops.push(op);
+
+                outer.replace_block(GraphAppend {
+                    block_id: end_block,
+                    ops: Pod::new(),
+                });
+
+                outer.ids.next_variable_id -= 1;
+
+                let kind = GraphOpKind::StackDealloc { count: 1 };
+                let op = GraphOp::new(kind, Type::Null, id);
+                outer.append.ops.push(op);
+
+                return Ok(NULL);
+            }
+
+            Break { label } | Continue { label } => {
+                let is_break = matches!(*expr, Break { .. });
+
+                let symbol = label_symbol(label);
+
+                let (depth, target_block, var_base) = match self.loop_info(symbol) {
+                    Some((depth, info)) if is_break => {
+                        info.broken.set(true);
+                        (depth, info.break_block, info.var_base)
+                    }
+                    Some((depth, info)) => (depth, info.continue_block, info.var_base),
+                    None if symbol.is_some() => {
+                        return Err(Error::new(
+                            "couldn't find a loop with this label",
+                            label.loc(),
+                        ));
+                    }
+                    None if is_break => {
+                        return Err(Error::new("break outside of a loop", id.loc()));
+                    }
+                    None => {
+                        return Err(Error::new("continue outside of a loop", id.loc()));
+                    }
+                };
+
+                self.emit_defers(depth)?;
+
+                // Variables declared inside the loop need to be cleaned up
+                // before jumping out of their scope
+                let count = self.ids.next_variable_id - var_base;
+                if count > 0 {
+                    let kind = GraphOpKind::StackDealloc { count };
+                    let op = GraphOp::new(kind, Type::Null, id);
+                    self.append.ops.push(op);
+                }
+
+                let kind = GraphOpKind::Jump {
+                    block: target_block,
+                };
+                let op = GraphOp::new(kind, Type::Null, id);
+                self.append.ops.push(op);
+
+                return Ok(Value::new(Operand::Null, Type::Never));
+            }
+
+            UnaryOp { kind, value } => {
+                use UnaryExprKind as Op;
+
+                // Negative literals like `-1` are handled as one literal
+                if literal_value(id).is_some() || float_value(id).is_some() {
+                    return self.check_expr_as(id, Type::Null);
+                }
+
+                if let Op::Ref = kind {
+                    let var_info = match *value {
+                        Ident { symbol } => self.search(symbol),
+                        _ => {
+                            let message = "can only take the address of variables";
+                            return Err(Error::new(message, value.loc()));
+                        }
+                    };
+
+                    let var_info = match var_info {
+                        Some(e) => e,
+                        None => {
+                            return Err(Error::new("couldn't find variable", value.loc()));
+                        }
+                    };
+
+                    let ty = self.types.pointer_to(var_info.ty);
+                    let target = self.var_address(id, var_info);
+
+                    return Ok(Value::new(target, ty));
+                }
+
+                if let Op::Deref = kind {
+                    let (pointer, pointee) = self.check_deref(value)?;
+
+                    return Ok(self.load(id, pointer.op, pointee));
+                }
+
+                let base = self.ids.next_op_id;
+                let operand = self.check_expr(ValueSlot::SaveSomewhere, value)?;
+
+                let is_integer = operand.ty.is_integer();
+                let is_float = operand.ty.is_float();
+                let is_bool = operand.ty == Type::Bool;
+
+                match kind {
+                    Op::Negate if !is_integer && !is_float => {
+                        return Err(Error::new("can only negate numbers", id.loc()));
+                    }
+                    Op::BitNot if !is_integer => {
+                        return Err(Error::new("can only flip the bits of integers", id.loc()));
+                    }
+                    Op::BoolNot if !is_bool => {
+                        return Err(Error::new("can only use `!` on bools", id.loc()));
+                    }
+                    _ => {}
+                }
+
+                let value = operand.op;
+
+                // -x is just 0 - x; the bits of 0 are also the bits of 0.0
+                let zero = match kind {
+                    Op::Negate => {
+                        self.release_registers(base, &[operand]);
+                        let zero = self.register_id();
+
+                        let kind = GraphOpKind::ConstantU64 {
+                            target: zero,
+                            value: 0,
+                        };
+                        let op = GraphOp::new(kind, operand.ty, id);
+                        self.append.ops.push(op);
+
+                        zero
+                    }
+                    _ => Operand::Null,
+                };
+
+                // Same as with binary operations, the operand gets read before
+                // the target gets written, so they can share a register
+                self.ids.next_op_id = base;
+                let target = self.register_id();
+
+                let (kind, ty) = match kind {
+                    Op::Negate => {
+                        let kind = GraphOpKind::Sub {
+                            target,
+                            left: zero,
+                            right: value,
+                        };
+
+                        (kind, operand.ty)
+                    }
+                    Op::BitNot => (GraphOpKind::BitNot { target, value }, operand.ty),
+                    Op::BoolNot => (GraphOpKind::BoolNot { target, value }, Type::Bool),
+                    Op::Ref | Op::Deref => unreachable!(),
+                };
+
+                let op = GraphOp::new(kind, ty, id);
+                self.append.ops.push(op);
+
+                return Ok(Value::new(target, ty));
+            }
+
+            BinaryOp { kind, left, right } => {
+                use BinaryExprKind as Op;
+
+                if let Op::BoolAnd | Op::BoolOr = kind {
+                    return self.check_short_circuit(id, kind, left, right);
+                }
+
+                let base = self.ids.next_op_id;
+                let (left_value, right_value) = self.check_operands(left, right)?;
+
+                return self.check_binary_op(id, kind, base, left_value, right_value);
+            }
+
+            Block(block) => {
+                return self.check_local_block(slot, id, Type::Null);
+            }
+
+            Defer { value } => {
+                let defer = DeferInfo {
+                    expr: value,
+                    vars: self.scope.vars.clone(),
+                };
+
+                self.scope.defers.push(defer);
+
+                return Ok(NULL);
+            }
+
+            Call { callee, args } => {
+                const PRINT: u32 = Key::Print as u32;
+
+                let symbol = match *callee {
+                    Ident { symbol: PRINT } => PRINT,
+                    Ident { symbol } => symbol,
+
+                    _ => {
+                        if let Some((ty, index)) = self.check_variant(callee)? {
+                            return self.check_variant_value(id, ty, index, args);
+                        }
+
+                        return Err(Error::new("can only call procedures by name", callee.loc()));
+                    }
+                };
+
+                if let Some(ty) = builtin_type(symbol) {
+                    return self.check_cast(id, ty, args);
+                }
+
+                if symbol == Key::Alloc as u32 {
+                    return self.check_alloc(id, args);
+                }
+
+                if symbol == Key::Free as u32 {
+                    return self.check_free(id, args);
+                }
+
+                if symbol != PRINT {
+                    return self.check_call(id, symbol, args);
+                }
+
+                self.check_each_reusing_regs(args, |env, arg| {
+                    let value = env.check_expr(ValueSlot::SaveSomewhere, arg)?;
+
+                    if value.ty.is_aggregate() {
+                        let message = "can't print arrays, slices, structs or enums with payloads";
+                        return Err(Error::new(message, arg.loc()));
+                    }
+
+                    let kind = GraphOpKind::Print { value: value.op };
+                    let op = GraphOp::new(kind, value.ty, arg);
+                    env.append.ops.push(op);
+
+                    return Ok(());
+                })?;
+
+                let op = GraphOp::new(GraphOpKind::PrintNewline, Type::Null, id);
+                self.append.ops.push(op);
+
+                return Ok(NULL);
+            }
+
+            Array { .. } => {
+                return self.check_array(id, Type::Null);
+            }
+
+            StructLiteral { .. } => {
+                return self.check_struct_literal(id);
+            }
+
+            Field { .. } => {
+                if let Some((ty, index)) = self.check_variant(id)? {
+                    return self.check_variant_value(id, ty, index, ExprRange::EMPTY);
+                }
+
+                let (value, symbol) = match *id {
+                    Field { value, symbol } => (value, symbol),
+                    _ => unreachable!(),
+                };
+
+                let base = self.check_expr(ValueSlot::SaveSomewhere, value)?;
+
+                if let Some((_, len)) = self.types.elem(base.ty) {
+                    if symbol == Key::Len as u32 {
+                        return Ok(self.check_len(id, base, len));
+                    }
+                }
+
+                let (pointer, ty) = self.field_pointer(id, base)?;
+
+                return Ok(self.load(id, pointer, ty));
+            }
+
+            Match { value, arms } => {
+                return self.check_match(slot, id, value, arms, Type::Null);
+            }
+
+            // Types are already handled at the start of the block
+            TypeDecl { .. } => {
+                return Ok(NULL);
+            }
+
+            Index { value, index } => {
+                if let Range { .. } = *index {
+                    return self.check_slice(id, value, index);
+                }
+
+                let (pointer, elem) = self.check_index(value, index)?;
+
+                return Ok(self.load(id, pointer, elem));
+            }
+
+            k => unimplemented!("{}", k.name()),
+        }
+    }
+
+    // Lowers a binary operation on two values that were already checked.
+    // Registers from `base` onwards were only used to compute the operands,
+    // so they get reused once the operation is done with them.
+    fn check_binary_op(
+        &mut self,
+        id: ExprId,
+        kind: BinaryExprKind,
+        base: u16,
+        left_value: Value,
+        right_value: Value,
+    ) -> Result<Value, Error> {
+        use BinaryExprKind as Op;
+
+        let is_offset = matches!(kind, Op::Add | Op::Subtract);
+        let is_pointer = matches!(left_value.ty, Type::Pointer(_));
+        if is_offset && is_pointer && right_value.ty.is_integer() {
+            return self.check_ptr_offset(id, kind, base, left_value, right_value);
+        }
+
+        if left_value.ty != right_value.ty {
+            return Err(Error::new(
+                "binary operation should be on values of similar type",
+                id.loc(),
+            ));
+        }
+
+        let operand_ty = left_value.ty;
+        let ty = match kind {
+            Op::Equal | Op::NotEqual => Type::Bool,
+            Op::Less | Op::LessEqual | Op::Greater | Op::GreaterEqual => Type::Bool,
+            _ => operand_ty,
+        };
+
+        let is_integer = operand_ty.is_integer();
+        let is_float = operand_ty.is_float();
+        let is_bool = operand_ty == Type::Bool;
+        let is_char = operand_ty == Type::Char;
+        let is_pointer = matches!(operand_ty, Type::Pointer(_));
+        let is_enum = matches!(operand_ty, Type::Enum(_));
+
+        let operands_ok = match kind {
+            Op::Equal | Op::NotEqual => {
+                is_integer || is_float || is_bool || is_char || is_pointer || is_enum
+            }
+            Op::Less | Op::LessEqual | Op::Greater | Op::GreaterEqual => {
+                is_integer || is_float || is_char
+            }
+            Op::BitAnd | Op::BitOr | Op::BitXor => is_integer || is_bool,
+            Op::ShiftLeft | Op::ShiftRight => is_integer,
+            _ => is_integer || is_float,
+        };
+
+        if !operands_ok {
+            return Err(Error::new(
+                "binary operation isn't supported for these values",
+                id.loc(),
+            ));
+        }
+
+        // The operands get read before the target gets written, so the
+        // target can be one of their registers
+        self.ids.next_op_id = base;
+
+        let target = self.register_id();
+        let (left, right) = (left_value.op, right_value.op);
+
+        let kind = match kind {
+            Op::Add => GraphOpKind::Add {
+                target,
+                left,
+                right,
+            },
+            Op::Subtract => GraphOpKind::Sub {
+                target,
+                left,
+                right,
+            },
+            Op::Multiply => GraphOpKind::Mul {
+                target,
+                left,
+                right,
+            },
+            Op::Divide => GraphOpKind::Div {
+                target,
+                left,
+                right,
+            },
+            Op::Modulo => GraphOpKind::Mod {
+                target,
+                left,
+                right,
+            },
+
+            Op::ShiftLeft => GraphOpKind::LShift {
+                target,
+                left,
+                right,
+            },
+            Op::ShiftRight => GraphOpKind::RShift {
+                target,
+                left,
+                right,
+            },
+            Op::BitAnd => GraphOpKind::BitAnd {
+                target,
+                left,
+                right,
+            },
+            Op::BitOr => GraphOpKind::BitOr {
+                target,
+                left,
+                right,
+            },
+            Op::BitXor => GraphOpKind::BitXor {
+                target,
+                left,
+                right,
+            },
+
+            Op::Less => GraphOpKind::CompLt {
+                target,
+                left,
+                right,
+            },
+            Op::LessEqual => GraphOpKind::CompLeq {
+                target,
+                left,
+                right,
+            },
+            Op::Equal => GraphOpKind::CompEq {
+                target,
+                left,
+                right,
+            },
+            Op::NotEqual => GraphOpKind::CompNeq {
+                target,
+                left,
+                right,
+            },
+
+            // a > b is just b < a
+            Op::Greater => GraphOpKind::CompLt {
+                target,
+                left: right,
+                right: left,
+            },
+            Op::GreaterEqual => GraphOpKind::CompLeq {
+                target,
+                left: right,
+                right: left,
+            },
+
+            Op::BoolAnd | Op::BoolOr => unreachable!(),
+        };
+
+        let op = GraphOp::new(kind, operand_ty, id);
+        self.append.ops.push(op);
+
+        return Ok(Value::new(target, ty));
+    }
+
+    // Integer literals without a suffix don't have a type of their own, so
+    // they take on the type they're expected to have, defaulting to u64 (or
+    // s64 for negative literals). Float literals do the same, defaulting to
+    // f64, and array literals pass the expected element type on to their
+    // elements. Everything else gets checked normally, and the caller is
+    // responsible for checking the resulting type.
+    fn check_expr_as(&mut self, id: ExprId, expected: Type) -> Result<Value, Error> {
+        match *id {
+            ExprKind::Array { .. } => return self.check_array(id, expected),
+            ExprKind::IfElse { .. } => {
+                return self.check_if_else(ValueSlot::SaveSomewhere, id, expected);
+            }
+            ExprKind::Match { value, arms } => {
+                return self.check_match(ValueSlot::SaveSomewhere, id, value, arms, expected);
+            }
+            ExprKind::Block(_) => {
+                return self.check_local_block(ValueSlot::SaveSomewhere, id, expected);
+            }
+            _ => {}
+        }
+
+        if let Some((value, suffix)) = float_value(id) {
+            return self.check_float_literal(id, value, suffix, expected);
+        }
+
+        let (value, suffix) = match literal_value(id) {
+            Some(literal) => literal,
+            None => return self.check_expr(ValueSlot::SaveSomewhere, id),
+        };
+
+        // Integer literals can be used as floats, e.g. `let a: f32 = 1`
+        if suffix.is_null() && expected.is_float() {
+            return self.check_float_literal(id, value as f64, suffix, expected);
+        }
+
+        let ty = if !suffix.is_null() {
+            self.check_type(suffix)?
+        } else if expected.is_integer() {
+            expected
+        } else if value < 0 {
+            Type::S64
+        } else {
+            Type::U64
+        };
+
+        if !ty.is_integer() {
+            return Err(Error::new(
+                "integer suffix should be an integer type",
+                suffix.loc(),
+            ));
+        }
+
+        let (min, max) = ty.int_range();
+        if value < min || value > max {
+            let message = format!("literal is out of range for {}", ty.name());
+
+            return Err(Error::new(message, id.loc()));
+        }
+
+        let target = self.register_id();
+
+        let kind = GraphOpKind::ConstantU64 {
+            target,
+            value: value as u64,
+        };
+        let op = GraphOp::new(kind, ty, id);
+        self.append.ops.push(op);
+
+        return Ok(Value::new(target, ty));
+    }
+
+    // Both arms of an if-else get the type the whole thing is expected to
+    // have, so that e.g. literals in them get the right type
+    fn check_if_else(
+        &mut self,
+        slot: ValueSlot,
+        id: ExprId,
+        expected: Type,
+    ) -> Result<Value, Error> {
+        let (cond, if_true, if_false) = match *id {
+            ExprKind::IfElse {
+                cond,
+                if_true,
+                if_false,
+            } => (cond, if_true, if_false),
+            _ => unreachable!(),
+        };
+
+        // The condition is done with once it's branched on
+        let next_op_id = self.ids.next_op_id;
+        let cond = self.check_cond(cond)?;
+
+        let result_slot = match slot {
+            ValueSlot::Delete => None,
+            _ => Some(self.declare_result_slot(id)),
+        };
+
+        let if_true_block = self.graph.get_block_id();
+        let if_false_block = self.graph.get_block_id();
+        let end_block = self.graph.get_block_id();
+
+        let kind = GraphOpKind::Branch {
+            cond: cond.op,
+            if_true: if_true_block,
+            if_false: if_false_block,
+        };
+        let op = GraphOp::new(kind, Type::Null, id);
+        self.append.ops.push(op);
+
+        let arms = [
+            Arm {
+                block_id: if_true_block,
+                expr: if_true,
+                pattern: ExprId::NULL,
+                value: NULL,
+            },
+            Arm {
+                block_id: if_false_block,
+                expr: if_false,
+                pattern: ExprId::NULL,
+                value: NULL,
+            },
+        ];
+
+        self.ids.next_op_id = next_op_id;
+
+        return self.check_arms(end_block, &arms, expected, result_slot);
+    }
+
+    // A block in its own scope, whose value is its last statement
+    fn check_local_block(
+        &mut self,
+        slot: ValueSlot,
+        id: ExprId,
+        expected: Type,
+    ) -> Result<Value, Error> {
+        let block = match *id {
+            ExprKind::Block(block) => block,
+            _ => unreachable!(),
+        };
+
+        let result_slot = match slot {
+            ValueSlot::Delete => None,
+            _ => Some(self.declare_result_slot(id)),
+        };
+
+        let var_base = self.ids.next_variable_id;
+        let mut child = self.chain_local();
+
+        let mut value = child.check_block(slot, &block, expected)?;
+
+        if value.ty != Type::Never {
+            child.emit_defers(1)?;
+        }
+
+        // Temporary stack space gets cleaned up here too
+        let count = child.ids.next_variable_id - var_base;
+        child.ids.next_variable_id = var_base;
+
+        if let Some(result_slot) = result_slot {
+            let mut saved = Type::Null;
+            if value.ty.is_aggregate() && count > 0 {
+                value = child.save_to_slot(id, result_slot, value);
+                saved = value.ty;
+            }
+
+            child.finish_result_slot(result_slot, saved);
+        }
+
+        let kind = GraphOpKind::StackDealloc { count };
+        let op = GraphOp::new(kind, Type::Null, id);
+        self.append.ops.push(op);
+
+        return Ok(value);
+    }
+
+    // Floats are stored as their bits
+    fn check_float_literal(
+        &mut self,
+        id: ExprId,
+        value: f64,
+        suffix: ExprId,
+        expected: Type,
+    ) -> Result<Value, Error> {
+        let ty = if !suffix.is_null() {
+            self.check_type(suffix)?
+        } else if expected.is_float() {
+            expected
+        } else {
+            Type::F64
+        };
+
+        let bits = match ty {
+            Type::F32 => (value as f32).to_bits() as u64,
+            _ => value.to_bits(),
+        };
+
+        let target = self.register_id();
+
+        let kind = GraphOpKind::ConstantU64 {
+            target,
+            value: bits,
+        };
+        let op = GraphOp::new(kind, ty, id);
+        self.append.ops.push(op);
+
+        return Ok(Value::new(target, ty));
+    }
+
+    // Checks both sides of a binary operation. If only one side is an untyped
+    // literal, it gets the type of the other side. Integer literals are less
+    // picky than float literals, so `1 + 0.5` is an f64.
+    fn check_operands(&mut self, left: ExprId, right: ExprId) -> Result<(Value, Value), Error> {
+        if untyped_literal(left) > untyped_literal(right) {
+            let right_value = self.check_expr(ValueSlot::SaveSomewhere, right)?;
+            let left_value = self.check_expr_as(left, right_value.ty)?;
+
+            return Ok((left_value, right_value));
+        }
+
+        let base = self.ids.next_op_id;
+        let left_value = self.check_expr(ValueSlot::SaveSomewhere, left)?;
+
+        let in_register = matches!(left_value.op, Operand::RegisterValue { .. });
+        let can_spill = in_register && !left_value.ty.is_aggregate();
+        if self.ids.next_op_id < SPILL_REGISTER_ID || !can_spill {
+            let right_value = self.check_expr_as(right, left_value.ty)?;
+
+            return Ok((left_value, right_value));
+        }
+
+        // Registers are running low, so the left side waits on the stack while
+        // the right side gets checked, and gets loaded back afterwards. The
+        // stack space is temporary, like for array literals.
+        let ty = left_value.ty;
+        let var_id = self.reserve_var_id();
+        let var = VariableInfo { id: var_id, ty };
+
+        let size = self.types.stack_size(ty);
+        let kind = GraphOpKind::DeclareStack { size };
+        let op = GraphOp::new(kind, ty, left);
+        self.append.ops.push(op);
+
+        self.write_var(left, var, left_value);
+        self.ids.next_op_id = base;
+
+        let right_value = self.check_expr_as(right, ty)?;
+
+        let target = self.register_id();
+        let kind = GraphOpKind::Mov {
+            target,
+            source: Operand::StackLocal { id: var_id },
+        };
+        let op = GraphOp::new(kind, ty, left);
+        self.append.ops.push(op);
+
+        return Ok((Value::new(target, ty), right_value));
+    }
+
+    // Checks the pointer in `*pointer`, returning it along with the type it
+    // points to
+    fn check_deref(&mut self, pointer: ExprId) -> Result<(Value, Type), Error> {
+        let value = self.check_expr(ValueSlot::SaveSomewhere, pointer)?;
+
+        let pointee = match self.types.pointee(value.ty) {
+            Some(pointee) => pointee,
+            None => {
+                return Err(Error::new("can only dereference pointers", pointer.loc()));
+            }
+        };
+
+        return Ok((value, pointee));
+    }
+
+    // `pointer + offset` moves the pointer by `offset` values, so the offset
+    // gets scaled by the size of the type being pointed to
+    fn check_ptr_offset(
+        &mut self,
+        id: ExprId,
+        kind: BinaryExprKind,
+        base: u16,
+        pointer: Value,
+        offset: Value,
+    ) -> Result<Value, Error> {
+        let pointee = match self.types.pointee(pointer.ty) {
+            Some(pointee) => pointee,
+            None => unreachable!(),
+        };
+
+        let size = self.types.size_of(pointee) as i64;
+        let scale = match kind {
+            BinaryExprKind::Subtract => -size,
+            _ => size,
+        };
+
+        // The operands need to stay alive until the last step, which is the
+        // only one that can reuse their registers
+        self.release_registers(base, &[pointer, offset]);
+
+        let wide = self.register_id();
+        let kind = GraphOpKind::Cast {
+            target: wide,
+            value: offset.op,
+            from: offset.ty,
+        };
+        let op = GraphOp::new(kind, Type::S64, id);
+        self.append.ops.push(op);
+
+        let scale_value = self.register_id();
+        let kind = GraphOpKind::ConstantU64 {
+            target: scale_value,
+            value: scale as u64,
+        };
+        let op = GraphOp::new(kind, Type::S64, id);
+        self.append.ops.push(op);
+
+        let bytes = self.register_id();
+        let kind = GraphOpKind::Mul {
+            target: bytes,
+            left: wide,
+            right: scale_value,
+        };
+        let op = GraphOp::new(kind, Type::S64, id);
+        self.append.ops.push(op);
+
+        self.ids.next_op_id = base;
+
+        let target = self.register_id();
+        let kind = GraphOpKind::PtrOffset {
+            target,
+            pointer: pointer.op,
+            offset: bytes,
+        };
+        let op = GraphOp::new(kind, pointer.ty, id);
+        self.append.ops.push(op);
+
+        return Ok(Value::new(target, pointer.ty));
+    }
+
+    // Array literals get their own stack space, which lives until the end of
+    // the statement or block they're in. Without an expected type, the first
+    // element decides the element type.
+    fn check_array(&mut self, id: ExprId, expected: Type) -> Result<Value, Error> {
+        let elems = match *id {
+            ExprKind::Array { elems } => elems,
+            _ => unreachable!(),
+        };
+
+        let expected_elem = match self.types.elem(expected) {
+            Some((elem, Some(_))) => elem,
+            _ => Type::Null,
+        };
+
+        let len = elems.len() as u32;
+        let mut elems = elems.into_iter();
+
+        let first = match elems.next() {
+            Some(first) => first,
+            None => unreachable!(),
+        };
+
+        let first_value = self.check_expr_as(first, expected_elem)?;
+        let elem = first_value.ty;
+        if let Type::Null | Type::Never = elem {
+            return Err(Error::new("array elements should be values", first.loc()));
+        }
+
+        let elem_size = self.types.size_of(elem);
+        if elem_size as u64 * len as u64 > u16::MAX as u64 {
+            return Err(Error::new("array is too large", id.loc()));
+        }
+
+        let ty = self.types.array_of(elem, len);
+        let var_id = self.reserve_var_id();
+        let var = VariableInfo { id: var_id, ty };
+
+        let size = self.types.stack_size(ty);
+        let kind = GraphOpKind::DeclareStack { size };
+        let op = GraphOp::new(kind, ty, id);
+        self.append.ops.push(op);
+
+        let base = self.var_address(id, var);
+        self.store(first, base, first_value);
+
+        let pointer_ty = self.types.pointer_to(elem);
+
+        self.check_each_reusing_regs(elems.enumerate(), |env, (index, expr)| {
+            let value = env.check_expr_as(expr, elem)?;
+            if value.ty != elem {
+                return Err(Error::new(
+                    "array elements should all have the same type",
+                    expr.loc(),
+                ));
+            }
+
+            let offset = (index as u64 + 1) * elem_size as u64;
+            let pointer = env.offset_pointer(expr, pointer_ty, base, offset);
+            env.store(expr, pointer, value);
+
+            return Ok(());
+        })?;
+
+        return Ok(Value::new(base, ty));
+    }
+
+    // Struct literals get temporary stack space, just like array literals,
+    // and every field needs to be given a value
+    fn check_struct_literal(&mut self, id: ExprId) -> Result<Value, Error> {
+        let (symbol, fields) = match *id {
+            ExprKind::StructLiteral { symbol, fields } => (symbol, fields),
+            _ => unreachable!(),
+        };
+
+        let ty = match self.search_type(symbol) {
+            Some(ty) => ty,
+            None => {
+                return Err(Error::new("couldn't find type", id.loc()));
+            }
+        };
+
+        let infos = match self.types.fields(ty) {
+            Some(infos) => infos.to_vec(),
+            None => {
+                return Err(Error::new("can only make literals of structs", id.loc()));
+            }
+        };
+
+        let var_id = self.reserve_var_id();
+        let var = VariableInfo { id: var_id, ty };
+
+        let size = self.types.stack_size(ty);
+        let kind = GraphOpKind::DeclareStack { size };
+        let op = GraphOp::new(kind, ty, id);
+        self.append.ops.push(op);
+
+        let base = self.var_address(id, var);
+
+        let mut written = vec![false; infos.len()];
+        self.check_each_reusing_regs(fields, |env, field| {
+            let (symbol, value) = match *field {
+                ExprKind::FieldValue { symbol, value } => (symbol, value),
+                _ => unreachable!(),
+            };
+
+            let index = match infos.iter().position(|info| info.symbol == symbol) {
+                Some(index) => index,
+                None => {
+                    return Err(Error::new("couldn't find field", field.loc()));
+                }
+            };
+
+            if written[index] {
+                return Err(Error::new("field was already given a value", field.loc()));
+            }
+
+            written[index] = true;
+
+            let info = infos[index];
+            let result = env.check_expr_as(value, info.ty)?;
+            if result.ty != info.ty {
+                return Err(Error::new(
+                    "value doesn't match the field's type",
+                    value.loc(),
+                ));
+            }
+
+            let pointer_ty = env.types.pointer_to(info.ty);
+            let pointer = env.offset_pointer(field, pointer_ty, base, info.offset as u64);
+            env.store(field, pointer, result);
+
+            return Ok(());
+        })?;
+
+        if written.contains(&false) {
+            return Err(Error::new("struct literal is missing fields", id.loc()));
+        }
+
+        return Ok(Value::new(base, ty));
+    }
+
+    // Checks the target of a compound assignment like `a[i] += 1`, returning
+    // a pointer to it along with its type, so that it only gets evaluated once
+    fn check_target(&mut self, target: ExprId) -> Result<(Operand, Type), Error> {
+        use ExprKind::*;
+
+        match *target {
+            Ident { symbol } => {
+                let var_info = match self.search(symbol) {
+                    Some(e) => e,
+                    None => {
+                        return Err(Error::new("couldn't find variable", target.loc()));
+                    }
+                };
+
+                let pointer = self.var_address(target, var_info);
+
+                return Ok((pointer, var_info.ty));
+            }
+
+            UnaryOp {
+                kind: UnaryExprKind::Deref,
+                value: pointer,
+            } => {
+                let (pointer, pointee) = self.check_deref(pointer)?;
+
+                return Ok((pointer.op, pointee));
+            }
+
+            Field { .. } => return self.check_field(target),
+
+            Index { value, index } if !matches!(*index, Range { .. }) => {
+                return self.check_index(value, index);
+            }
+
+            _ => {
+                return Err(Error::new("can't assign to this", target.loc()));
+            }
+        }
+    }
+
+    // Checks `value.field`, returning a pointer to the field along with the
+    // field's type
+    fn check_field(&mut self, id: ExprId) -> Result<(Operand, Type), Error> {
+        let (value, symbol) = match *id {
+            ExprKind::Field { value, symbol } => (value, symbol),
+            _ => unreachable!(),
+        };
+
+        let base = self.check_expr(ValueSlot::SaveSomewhere, value)?;
+
+        if self.types.elem(base.ty).is_some() && symbol == Key::Len as u32 {
+            return Err(Error::new(
+                "the length of an array or slice can't be changed",
+                id.loc(),
+            ));
+        }
+
+        return self.field_pointer(id, base);
+    }
+
+    fn field_pointer(&mut self, id: ExprId, base: Value) -> Result<(Operand, Type), Error> {
+        let (value, symbol) = match *id {
+            ExprKind::Field { value, symbol } => (value, symbol),
+            _ => unreachable!(),
+        };
+
+        let infos = match self.types.fields(base.ty) {
+            Some(infos) => infos,
+            None => {
+                return Err(Error::new("can only get fields of structs", value.loc()));
+            }
+        };
+
+        let info = match infos.iter().find(|info| info.symbol == symbol) {
+            Some(&info) => info,
+            None => {
+                return Err(Error::new("couldn't find field", id.loc()));
+            }
+        };
+
+        let pointer_ty = self.types.pointer_to(info.ty);
+        let pointer = self.offset_pointer(id, pointer_ty, base.op, info.offset as u64);
+
+        return Ok((pointer, info.ty));
+    }
+
+    // `value.len` is the number of elements, which is part of the type for
+    // arrays, and stored after the data pointer for slices
+    fn check_len(&mut self, id: ExprId, base: Value, len: Option<u32>) -> Value {
+        if let Some(len) = len {
+            let target = self.register_id();
+            let kind = GraphOpKind::ConstantU64 {
+                target,
+                value: len as u64,
+            };
+            let op = GraphOp::new(kind, Type::U64, id);
+            self.append.ops.push(op);
+
+            return Value::new(target, Type::U64);
+        }
+
+        let len_ty = self.types.pointer_to(Type::U64);
+        let len_pointer = self.offset_pointer(id, len_ty, base.op, 8);
+
+        return self.load(id, len_pointer, Type::U64);
+    }
+
+    // Payload types can be declared later in the block, so the payloads get
+    // checked along with the rest of the layout in require_layout
+    fn check_enum_type(&mut self, decl: ExprId, variants: ExprRange) -> Result<Type, Error> {
+        let mut symbols = Vec::new();
+        let mut has_payload = false;
+        for variant in variants {
+            let symbol = match *variant {
+                ExprKind::Variant { symbol, payload } => {
+                    has_payload = has_payload || !payload.is_empty();
+                    symbol
+                }
+                _ => unreachable!(),
+            };
+
+            if symbols.contains(&symbol) {
+                return Err(Error::new("redeclared variant", variant.loc()));
+            }
+
+            symbols.push(symbol);
+        }
+
+        return Ok(self.types.add_enum(decl, symbols, has_payload));
+    }
+
+    // Checks whether `Name.Variant` is a variant of the enum `Name`, and if so
+    // returns the enum type and the index of the variant. Variables shadow
+    // types, so `a.b` is still a field access if there's a variable `a`.
+    fn check_variant(&mut self, id: ExprId) -> Result<Option<(Type, u32)>, Error> {
+        let (value, symbol) = match *id {
+            ExprKind::Field { value, symbol } => (value, symbol),
+            _ => return Ok(None),
+        };
+
+        let type_symbol = match *value {
+            ExprKind::Ident { symbol } => symbol,
+            _ => return Ok(None),
+        };
+
+        if self.search(type_symbol).is_some() {
+            return Ok(None);
+        }
+
+        let ty = match self.search_type(type_symbol) {
+            Some(ty) => ty,
+            None => return Ok(None),
+        };
+
+        let variants = match self.types.variants(ty) {
+            Some(variants) => variants,
+            None => {
+                return Err(Error::new("can only get variants of enums", value.loc()));
+            }
+        };
+
+        return match variants.iter().position(|v| v.symbol == symbol) {
+            Some(index) => Ok(Some((ty, index as u32))),
+            None => Err(Error::new("couldn't find variant", id.loc())),
+        };
+    }
+
+    // Enums without payloads are just their variant index. Enums with
+    // payloads go on the stack, with the variant index followed by the
+    // payload values.
+    fn check_variant_value(
+        &mut self,
+        id: ExprId,
+        ty: Type,
+        index: u32,
+        args: ExprRange,
+    ) -> Result<Value, Error> {
+        let payload = match self.types.variants(ty) {
+            Some(variants) => variants[index as usize].payload.clone(),
+            None => unreachable!(),
+        };
+
+        if args.len() != payload.len() {
+            let message = format!(
+                "variant takes {} values but {} were given",
+                payload.len(),
+                args.len()
+            );
+            return Err(Error::new(message, id.loc()));
+        }
+
+        let tag = self.register_id();
+        let kind = GraphOpKind::ConstantU64 {
+            target: tag,
+            value: index as u64,
+        };
+
+        if let Type::Enum(_) = ty {
+            let op = GraphOp::new(kind, ty, id);
+            self.append.ops.push(op);
+
+            return Ok(Value::new(tag, ty));
+        }
+
+        let op = GraphOp::new(kind, Type::U32, id);
+        self.append.ops.push(op);
+
+        let var_id = self.reserve_var_id();
+        let var = VariableInfo { id: var_id, ty };
+
+        let size = self.types.stack_size(ty);
+        let kind = GraphOpKind::DeclareStack { size };
+        let op = GraphOp::new(kind, ty, id);
+        self.append.ops.push(op);
+
+        let base = self.var_address(id, var);
+        self.store(id, base, Value::new(tag, Type::U32));
+
+        let payload_args = args.into_iter().zip(payload);
+        self.check_each_reusing_regs(payload_args, |env, (arg, (value_ty, offset))| {
+            let result = env.check_expr_as(arg, value_ty)?;
+            if result.ty != value_ty {
+                return Err(Error::new(
+                    "value doesn't match the variant's payload type",
+                    arg.loc(),
+                ));
+            }
+
+            let pointer_ty = env.types.pointer_to(value_ty);
+            let pointer = env.offset_pointer(arg, pointer_ty, base, offset as u64);
+            env.store(arg, pointer, result);
+
+            return Ok(());
+        })?;
+
+        return Ok(Value::new(base, ty));
+    }
+
+    // Each arm gets tested in order with a chain of comparisons, and the
+    // first one that matches gets run. Every value needs to be handled by
+    // some arm, and every arm needs to handle something that the arms before
+    // it don't.
+    fn check_match(
+        &mut self,
+        slot: ValueSlot,
+        id: ExprId,
+        value: ExprId,
+        arms: ExprRange,
+        expected: Type,
+    ) -> Result<Value, Error> {
+        let value_loc = value.loc();
+        let value = self.check_expr(ValueSlot::SaveSomewhere, value)?;
+
+        let variant_count = match self.types.variants(value.ty) {
+            Some(variants) => variants.len(),
+            None => {
+                return Err(Error::new("can only match on enums", value_loc));
+            }
+        };
+
+        let mut rows = Vec::new();
+        let mut has_default = false;
+
+        for arm in arms {
+            let pattern = match *arm {
+                ExprKind::MatchArm { pattern, .. } => pattern,
+                _ => unreachable!(),
+            };
+
+            if has_default {
+                let message = "arms after a `_` or name pattern can't ever run";
+                return Err(Error::new(message, arm.loc()));
+            }
+
+            let row = vec![self.check_pattern(pattern, value.ty)?];
+
+            if let Pattern::Any = row[0] {
+                has_default = true;
+            } else if self.useful(&rows, &row, &[value.ty]).is_none() {
+                return Err(Error::new("variant was already matched", pattern.loc()));
+            }
+
+            rows.push(row);
+        }
+
+        if !has_default {
+            let mut missing = Vec::new();
+            for index in 0..variant_count {
+                let arity = match self.types.variants(value.ty) {
+                    Some(variants) => variants[index].payload.len(),
+                    None => unreachable!(),
+                };
+
+                let row = [Pattern::Variant {
+                    index: index as u32,
+                    payload: vec![Pattern::Any; arity],
+                }];
+
+                if let Some(mut witness) = self.useful(&rows, &row, &[value.ty]) {
+                    missing.push(witness.remove(0));
+                }
+            }
+
+            if !missing.is_empty() {
+                let message = format!("match is missing variants: {}", missing.join(", "));
+                return Err(Error::new(message, value_loc));
+            }
+        }
+
+        let result_slot = match slot {
+            ValueSlot::Delete => None,
+            _ => Some(self.declare_result_slot(id)),
+        };
+
+        let end_block = self.graph.get_block_id();
+        let mut match_arms = Vec::new();
+
+        // Each test is done with its registers once it jumps somewhere, so
+        // they can get reused by the next one
+        let next_op_id = self.ids.next_op_id;
+
+        for (arm, row) in arms.into_iter().zip(rows.iter()) {
+            let (pattern, body) = match *arm {
+                ExprKind::MatchArm { pattern, body } => (pattern, body),
+                _ => unreachable!(),
+            };
+
+            let block_id = self.graph.get_block_id();
+            match_arms.push(Arm {
+                block_id,
+                expr: body,
+                pattern,
+                value,
+            });
+
+            // The last arm doesn't need a test, since the match is
+            // exhaustive, so nothing else could have matched
+            if match_arms.len() == rows.len() {
+                let kind = GraphOpKind::Jump { block: block_id };
+                let op = GraphOp::new(kind, Type::Null, arm);
+                self.append.ops.push(op);
+
+                break;
+            }
+
+            let next_block = self.graph.get_block_id();
+            self.emit_test(arm, &row[0], value, block_id, next_block);
+            self.ids.next_op_id = next_op_id;
+
+            self.replace_block(GraphAppend {
+                block_id: next_block,
+                ops: Pod::new(),
+            });
+        }
+
+        return self.check_arms(end_block, &match_arms, expected, result_slot);
+    }
+
+    // Patterns are `_`, a name to bind the value to, or an enum variant with
+    // a pattern for each of its payload values, like `Shape.Rect(w, _)`
+    fn check_pattern(&mut self, pattern: ExprId, ty: Type) -> Result<Pattern, Error> {
+        let (variant, args) = match *pattern {
+            ExprKind::Ident { .. } => return Ok(Pattern::Any),
+            ExprKind::Call { callee, args } => (callee, args),
+            _ => (pattern, ExprRange::EMPTY),
+        };
+
+        let (variant_ty, index) = match self.check_variant(variant)? {
+            Some(variant) => variant,
+            None => {
+                let message = "patterns should be enum variants like `Color.Red`, names, or `_`";
+                return Err(Error::new(message, pattern.loc()));
+            }
+        };
+
+        if variant_ty != ty {
+            return Err(Error::new(
+                "pattern doesn't match the type of the value",
+                pattern.loc(),
+            ));
+        }
+
+        let payload_types: Vec<Type> = match self.types.variants(ty) {
+            Some(variants) => variants[index as usize]
+                .payload
+                .iter()
+                .map(|p| p.0)
+                .collect(),
+            None => unreachable!(),
+        };
+
+        if args.len() != payload_types.len() {
+            let message = format!(
+                "variant has {} values but the pattern has {}",
+                payload_types.len(),
+                args.len()
+            );
+            return Err(Error::new(message, pattern.loc()));
+        }
+
+        let mut payload = Vec::new();
+        for (arg, ty) in args.into_iter().zip(payload_types) {
+            payload.push(self.check_pattern(arg, ty)?);
+        }
+
+        return Ok(Pattern::Variant { index, payload });
+    }
+
+    // Looks for a value that `row` matches but none of `rows` do, which is
+    // how arms that can't ever run and values that no arm handles get found.
+    // Each row has a pattern per column, and the value that's found is
+    // returned as text, also with one pattern per column.
+    //
+    // This is the usefulness algorithm from "Warnings for pattern matching"
+    // by Luc Maranget.
+    fn useful(&self, rows: &[Vec<Pattern>], row: &[Pattern], tys: &[Type]) -> Option<Vec<String>> {
+        let (first, rest) = match row.split_first() {
+            Some(split) => split,
+            None => {
+                return match rows.is_empty() {
+                    true => Some(Vec::new()),
+                    false => None,
+                };
+            }
+        };
+
+        if let Pattern::Variant { index, payload } = first {
+            return self.useful_variant(rows, *index, payload, rest, tys);
+        }
+
+        // Only enums have variants, other columns only ever have `_` and names
+        let variants = self.types.variants(tys[0]).unwrap_or(&[]);
+        let starts_with = |index: usize| {
+            return rows
+                .iter()
+                .any(|r| matches!(r[0], Pattern::Variant { index: i, .. } if i as usize == index));
+        };
+
+        let missing = (0..variants.len()).find(|&index| !starts_with(index));
+
+        // If every variant shows up somewhere, then a value that's useful
+        // has to be useful for one of the variants
+        if !variants.is_empty() && missing.is_none() {
+            for (index, variant) in variants.iter().enumerate() {
+                let payload = vec![Pattern::Any; variant.payload.len()];
+                let witness = self.useful_variant(rows, index as u32, &payload, rest, tys);
+                if witness.is_some() {
+                    return witness;
+                }
+            }
+
+            return None;
+        }
+
+        // Otherwise only the rows that match anything in this column matter
+        let defaults: Vec<Vec<Pattern>> = rows
+            .iter()
+            .filter(|r| matches!(r[0], Pattern::Any))
+            .map(|r| r[1..].to_vec())
+            .collect();
+
+        let mut witness = self.useful(&defaults, rest, &tys[1..])?;
+
+        let text = match missing {
+            Some(index) => {
+                let variant = &variants[index];
+                let payload = vec!["_".to_string(); variant.payload.len()];
+                self.variant_text(variant.symbol, payload)
+            }
+            None => "_".to_string(),
+        };
+
+        witness.insert(0, text);
+
+        return Some(witness);
+    }
+
+    // Usefulness when `row` starts with a variant. Only the rows that could
+    // start with the same variant matter, and the payload patterns take the
+    // place of the first column.
+    fn useful_variant(
+        &self,
+        rows: &[Vec<Pattern>],
+        index: u32,
+        payload: &[Pattern],
+        rest: &[Pattern],
+        tys: &[Type],
+    ) -> Option<Vec<String>> {
+        let variant = match self.types.variants(tys[0]) {
+            Some(variants) => &variants[index as usize],
+            None => unreachable!(),
+        };
+
+        let mut payload_tys: Vec<Type> = variant.payload.iter().map(|p| p.0).collect();
+        payload_tys.extend_from_slice(&tys[1..]);
+
+        let mut payload_rows = Vec::new();
+        for r in rows {
+            let mut payload_row = match &r[0] {
+                Pattern::Any => vec![Pattern::Any; payload.len()],
+                Pattern::Variant { index: i, payload } if *i == index => payload.clone(),
+                Pattern::Variant { .. } => continue,
+            };
+
+            payload_row.extend_from_slice(&r[1..]);
+            payload_rows.push(payload_row);
+        }
+
+        let mut payload_row = payload.to_vec();
+        payload_row.extend_from_slice(rest);
+
+        let mut witness = self.useful(&payload_rows, &payload_row, &payload_tys)?;
+        let rest_witness = witness.split_off(payload.len());
+
+        let mut result = vec![self.variant_text(variant.symbol, witness)];
+        result.extend(rest_witness);
+
+        return Some(result);
+    }
+
+    fn variant_text(&self, symbol: u32, payload: Vec<String>) -> String {
+        let name = self.table.names[symbol];
+        if payload.is_empty() {
+            return name.to_string();
+        }
+
+        return format!("{}({})", name, payload.join(", "));
+    }
+
+    // Jumps to `pass_block` if `value` matches the pattern, and to
+    // `fail_block` if it doesn't. Payload values only get looked at after
+    // the variant index matches.
+    fn emit_test(
+        &mut self,
+        id: ExprId,
+        pattern: &Pattern,
+        value: Value,
+        pass_block: u32,
+        fail_block: u32,
+    ) {
+        let (index, payload) = match pattern {
+            Pattern::Variant { index, payload } => (*index, payload),
+            Pattern::Any => {
+                let kind = GraphOpKind::Jump { block: pass_block };
+                let op = GraphOp::new(kind, Type::Null, id);
+                self.append.ops.push(op);
+
+                return;
+            }
+        };
+
+        let (tag, tag_ty) = match value.ty {
+            Type::Union(_) => (self.load(id, value.op, Type::U32).op, Type::U32),
+            _ => (value.op, value.ty),
+        };
+
+        let variant = self.register_id();
+        let kind = GraphOpKind::ConstantU64 {
+            target: variant,
+            value: index as u64,
+        };
+        let op = GraphOp::new(kind, tag_ty, id);
+        self.append.ops.push(op);
+
+        let cond = self.register_id();
+        let kind = GraphOpKind::CompEq {
+            target: cond,
+            left: tag,
+            right: variant,
+        };
+        let op = GraphOp::new(kind, tag_ty, id);
+        self.append.ops.push(op);
+
+        let layout = match self.types.variants(value.ty) {
+            Some(variants) => variants[index as usize].payload.clone(),
+            None => unreachable!(),
+        };
+
+        let mut tests = Vec::new();
+        for (pattern, &(ty, offset)) in payload.iter().zip(layout.iter()) {
+            if let Pattern::Variant { .. } = pattern {
+                tests.push((pattern, ty, offset));
+            }
+        }
+
+        let mut next_block = match tests.is_empty() {
+            true => pass_block,
+            false => self.graph.get_block_id(),
+        };
+
+        let kind = GraphOpKind::Branch {
+            cond,
+            if_true: next_block,
+            if_false: fail_block,
+        };
+        let op = GraphOp::new(kind, Type::Null, id);
+        self.append.ops.push(op);
+
+        let next_op_id = self.ids.next_op_id;
+        let test_count = tests.len();
+        for (i, (pattern, ty, offset)) in tests.into_iter().enumerate() {
+            self.replace_block(GraphAppend {
+                block_id: next_block,
+                ops: Pod::new(),
+            });
+
+            next_block = match i + 1 == test_count {
+                true => pass_block,
+                false => self.graph.get_block_id(),
+            };
+
+            let pointer_ty = self.types.pointer_to(ty);
+            let pointer = self.offset_pointer(id, pointer_ty, value.op, offset as u64);
+            let field = self.load(id, pointer, ty);
+
+            self.emit_test(id, pattern, field, next_block, fail_block);
+            self.ids.next_op_id = next_op_id;
+        }
+    }
+
+    // Declares the names in a match arm's pattern as variables that hold the
+    // parts of the value they matched
+    fn bind_pattern(&mut self, pattern: ExprId, value: Value) -> Result<(), Error> {
+        let mut bindings = Vec::new();
+        self.find_bindings(pattern, value, &mut bindings)?;
+
+        for (id, symbol, value) in bindings {
+            let var_id = self.reserve_var_id();
+            let info = VariableInfo {
+                id: var_id,
+                ty: value.ty,
+            };
+            self.declare(id, symbol, info)?;
+
+            let size = self.types.stack_size(value.ty);
+            let kind = GraphOpKind::DeclareStack { size };
+            let op = GraphOp::new(kind, value.ty, id);
+            self.append.ops.push(op);
+
+            self.write_var(id, info, value);
+        }
+
+        return Ok(());
+    }
+
+    // The names are only declared after all of them are found, so that they
+    // can't shadow the enum names in the rest of the pattern
+    fn find_bindings(
+        &mut self,
+        pattern: ExprId,
+        value: Value,
+        bindings: &mut Vec<(ExprId, u32, Value)>,
+    ) -> Result<(), Error> {
+        const UNDERSCORE: u32 = Key::Underscore as u32;
+
+        let (variant, args) = match *pattern {
+            ExprKind::Ident { symbol: UNDERSCORE } => return Ok(()),
+            ExprKind::Ident { symbol } => {
+                bindings.push((pattern, symbol, value));
+                return Ok(());
+            }
+            ExprKind::Call { callee, args } => (callee, args),
+            _ => return Ok(()),
+        };
+
+        let layout = match self.check_variant(variant)? {
+            Some((ty, index)) => match self.types.variants(ty) {
+                Some(variants) => variants[index as usize].payload.clone(),
+                None => unreachable!(),
+            },
+            None => unreachable!(),
+        };
+
+        for (arg, (ty, offset)) in args.into_iter().zip(layout) {
+            if let ExprKind::Ident { symbol: UNDERSCORE } = *arg {
+                continue;
+            }
+
+            let pointer_ty = self.types.pointer_to(ty);
+            let pointer = self.offset_pointer(arg, pointer_ty, value.op, offset as u64);
+            let field = self.load(arg, pointer, ty);
+
+            self.find_bindings(arg, field, bindings)?;
+        }
+
+        return Ok(());
+    }
+
+    // Makes sure the size of `ty` is known, by computing the layout of the
+    // struct or enum it is or contains, if there is one
+    fn require_layout(&mut self, id: ExprId, ty: Type) -> Result<(), Error> {
+        if let Some((elem, Some(_))) = self.types.elem(ty) {
+            return self.require_layout(id, elem);
+        }
+
+        let (layout, message) = match ty {
+            Type::Struct(struct_id) => (
+                self.types.structs[struct_id as usize].layout,
+                "structs can't contain themselves",
+            ),
+            Type::Union(enum_id) => (
+                self.types.enums[enum_id as usize].layout,
+                "enums can't contain themselves",
+            ),
+            _ => return Ok(()),
+        };
+
+        match layout {
+            Layout::Done => return Ok(()),
+            Layout::InProgress => return Err(Error::new(message, id.loc())),
+            Layout::NotStarted => {}
+        }
+
+        return match ty {
+            Type::Struct(struct_id) => self.layout_struct(struct_id as usize),
+            Type::Union(enum_id) => self.layout_union(enum_id as usize),
+            _ => unreachable!(),
+        };
+    }
+
+    // Fields are laid out in order, each one aligned to its own alignment
+    fn layout_struct(&mut self, struct_id: usize) -> Result<(), Error> {
+        let decl = self.types.structs[struct_id].decl;
+        self.types.structs[struct_id].layout = Layout::InProgress;
+
+        let params = match *decl {
+            ExprKind::TypeDecl { ty, .. } => match *ty {
+                ExprKind::StructType { fields } => fields,
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        };
+
+        let mut fields: Vec<FieldInfo> = Vec::new();
+        let mut offset = 0;
+        let mut align = 1;
+
+        for param in params {
+            let (symbol, ty) = match *param {
+                ExprKind::Param { symbol, ty } => (symbol, ty),
+                _ => unreachable!(),
+            };
+
+            if fields.iter().any(|field| field.symbol == symbol) {
+                return Err(Error::new("redeclared field", param.loc()));
+            }
+
+            let ty = self.check_type(ty)?;
+            self.require_layout(param, ty)?;
+
+            let field_align = self.types.align_of(ty);
+            offset = align_up(offset, field_align);
+
+            fields.push(FieldInfo { symbol, ty, offset });
+
+            offset += self.types.size_of(ty);
+            align = core::cmp::max(align, field_align);
+        }
+
+        // Structs live on the stack, and stack variables can only be so big
+        let size = align_up(offset, align);
+        if size > u16::MAX as u32 {
+            return Err(Error::new("struct is too large", decl.loc()));
+        }
+
+        let info = &mut self.types.structs[struct_id];
+        info.layout = Layout::Done;
+        info.fields = fields;
+        info.size = size;
+        info.align = align;
+
+        return Ok(());
+    }
+
+    // The variant index goes first, and then each variant's payload gets laid
+    // out after it like the fields of a struct, all sharing the same space
+    fn layout_union(&mut self, enum_id: usize) -> Result<(), Error> {
+        let decl = self.types.enums[enum_id].decl;
+        self.types.enums[enum_id].layout = Layout::InProgress;
+
+        let variants = match *decl {
+            ExprKind::TypeDecl { ty, .. } => match *ty {
+                ExprKind::EnumType { variants } => variants,
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        };
+
+        let mut size = 4;
+        let mut align = 4;
+
+        for (index, variant) in variants.into_iter().enumerate() {
+            let types = match *variant {
+                ExprKind::Variant { payload, .. } => payload,
+                _ => unreachable!(),
+            };
+
+            let mut payload = Vec::new();
+            let mut offset = 4;
+
+            for ty_id in types {
+                let ty = self.check_type(ty_id)?;
+                self.require_layout(ty_id, ty)?;
+
+                let value_align = self.types.align_of(ty);
+                offset = align_up(offset, value_align);
+
+                payload.push((ty, offset));
+
+                offset += self.types.size_of(ty);
+                align = core::cmp::max(align, value_align);
+            }
+
+            size = core::cmp::max(size, offset);
+            self.types.enums[enum_id].variants[index].payload = payload;
+        }
+
+        let size = align_up(size, align);
+        if size > u16::MAX as u32 {
+            return Err(Error::new("enum is too large", decl.loc()));
+        }
+
+        let info = &mut self.types.enums[enum_id];
+        info.layout = Layout::Done;
+        info.size = size;
+        info.align = align;
+
+        return Ok(());
+    }
+
+    // Checks `value[index]`, returning a pointer to the element along with
+    // the element's type. The index gets checked against the length at
+    // runtime.
+    fn check_index(&mut self, value: ExprId, index: ExprId) -> Result<(Operand, Type), Error> {
+        let (data, elem, len) = self.check_indexable(value)?;
+        let index_value = self.check_index_value(index)?;
+
+        let kind = GraphOpKind::IndexCheck {
+            index: index_value,
+            len,
+        };
+        let op = GraphOp::new(kind, Type::S64, index);
+        self.append.ops.push(op);
+
+        let bytes = self.scale_index(index, index_value, elem);
+
+        let ty = self.types.pointer_to(elem);
+        let target = self.register_id();
+        let kind = GraphOpKind::PtrOffset {
+            target,
+            pointer: data,
+            offset: bytes,
+        };
+        let op = GraphOp::new(kind, ty, index);
+        self.append.ops.push(op);
+
+        return Ok((target, elem));
+    }
+
+    // `value[start..end]` makes a new slice that points into `value`. The
+    // slice itself gets temporary stack space, like an array literal.
+    fn check_slice(&mut self, id: ExprId, value: ExprId, range: ExprId) -> Result<Value, Error> {
+        let (start, end) = match *range {
+            ExprKind::Range { start, end } => (start, end),
+            _ => unreachable!(),
+        };
+
+        let (data, elem, len) = self.check_indexable(value)?;
+        let start_value = self.check_index_value(start)?;
+        let end_value = self.check_index_value(end)?;
+
+        let kind = GraphOpKind::RangeCheck {
+            start: start_value,
+            end: end_value,
+            len,
+        };
+        let op = GraphOp::new(kind, Type::S64, range);
+        self.append.ops.push(op);
+
+        let bytes = self.scale_index(start, start_value, elem);
+
+        let pointer_ty = self.types.pointer_to(elem);
+        let pointer = self.register_id();
+        let kind = GraphOpKind::PtrOffset {
+            target: pointer,
+            pointer: data,
+            offset: bytes,
+        };
+        let op = GraphOp::new(kind, pointer_ty, id);
+        self.append.ops.push(op);
+
+        let count = self.register_id();
+        let kind = GraphOpKind::Sub {
+            target: count,
+            left: end_value,
+            right: start_value,
+        };
+        let op = GraphOp::new(kind, Type::S64, id);
+        self.append.ops.push(op);
+
+        let ty = self.types.slice_of(elem);
+        let var_id = self.reserve_var_id();
+        let var = VariableInfo { id: var_id, ty };
+
+        let size = self.types.stack_size(ty);
+        let kind = GraphOpKind::DeclareStack { size };
+        let op = GraphOp::new(kind, ty, id);
+        self.append.ops.push(op);
+
+        let header = self.var_address(id, var);
+        self.store(id, header, Value::new(pointer, pointer_ty));
+
+        let len_ty = self.types.pointer_to(Type::U64);
+        let len_pointer = self.offset_pointer(id, len_ty, header, 8);
+        self.store(id, len_pointer, Value::new(count, Type::U64));
+
+        return Ok(Value::new(header, ty));
+    }
+
+    // Checks something that can be indexed, and returns a pointer to its first
+    // element, the element type, and the length as an s64
+    fn check_indexable(&mut self, value: ExprId) -> Result<(Operand, Type, Operand), Error> {
+        let array = self.check_expr(ValueSlot::SaveSomewhere, value)?;
+
+        let (elem, len) = match self.types.elem(array.ty) {
+            Some(info) => info,
+            None => {
+                return Err(Error::new("can only index arrays and slices", value.loc()));
+            }
+        };
+
+        if let Some(len) = len {
+            let len_value = self.register_id();
+            let kind = GraphOpKind::ConstantU64 {
+                target: len_value,
+                value: len as u64,
+            };
+            let op = GraphOp::new(kind, Type::S64, value);
+            self.append.ops.push(op);
+
+            return Ok((array.op, elem, len_value));
+        }
+
+        // Slices are a pointer to their elements, and then their length
+        let pointer_ty = self.types.pointer_to(elem);
+        let data = self.load(value, array.op, pointer_ty);
+
+        let len_ty = self.types.pointer_to(Type::S64);
+        let len_pointer = self.offset_pointer(value, len_ty, array.op, 8);
+        let len_value = self.load(value, len_pointer, Type::S64);
+
+        return Ok((data.op, elem, len_value.op));
+    }
+
+    // Indices can be any integer type, but they get widened to s64 for
+    // bounds checks and pointer math
+    fn check_index_value(&mut self, index: ExprId) -> Result<Operand, Error> {
+        let value = self.check_expr_as(index, Type::S64)?;
+        if !value.ty.is_integer() {
+            return Err(Error::new("indices should be integers", index.loc()));
+        }
+
+        let wide = self.register_id();
+        let kind = GraphOpKind::Cast {
+            target: wide,
+            value: value.op,
+            from: value.ty,
+        };
+        let op = GraphOp::new(kind, Type::S64, index);
+        self.append.ops.push(op);
+
+        return Ok(wide);
+    }
+
+    // Byte offset of the element at `index`
+    fn scale_index(&mut self, id: ExprId, index: Operand, elem: Type) -> Operand {
+        let size = self.register_id();
+        let kind = GraphOpKind::ConstantU64 {
+            target: size,
+            value: self.types.size_of(elem) as u64,
+        };
+        let op = GraphOp::new(kind, Type::S64, id);
+        self.append.ops.push(op);
+
+        let bytes = self.register_id();
+        let kind = GraphOpKind::Mul {
+            target: bytes,
+            left: index,
+            right: size,
+        };
+        let op = GraphOp::new(kind, Type::S64, id);
+        self.append.ops.push(op);
+
+        return bytes;
+    }
+
+    // Pointer to `offset` bytes past `pointer`, with type `ty`
+    fn offset_pointer(&mut self, id: ExprId, ty: Type, pointer: Operand, offset: u64) -> Operand {
+        if offset == 0 {
+            return pointer;
+        }
+
+        let offset_value = self.register_id();
+        let kind = GraphOpKind::ConstantU64 {
+            target: offset_value,
+            value: offset,
+        };
+        let op = GraphOp::new(kind, Type::S64, id);
+        self.append.ops.push(op);
+
+        let target = self.register_id();
+        let kind = GraphOpKind::PtrOffset {
+            target,
+            pointer,
+            offset: offset_value,
+        };
+        let op = GraphOp::new(kind, ty, id);
+        self.append.ops.push(op);
+
+        return target;
+    }
+
+    // Values that don't fit in a register are already pointers to their
+    // memory, so there's nothing to read
+    fn load(&mut self, id: ExprId, pointer: Operand, ty: Type) -> Value {
+        if ty.is_aggregate() {
+            return Value::new(pointer, ty);
+        }
+
+        let target = self.register_id();
+        let kind = GraphOpKind::Load { target, pointer };
+        let op = GraphOp::new(kind, ty, id);
+        self.append.ops.push(op);
+
+        return Value::new(target, ty);
+    }
+
+    // Values that don't fit in a register get copied over
+    fn store(&mut self, id: ExprId, pointer: Operand, value: Value) {
+        let kind = match value.ty.is_aggregate() {
+            true => GraphOpKind::MemCopy {
+                dest: pointer,
+                source: value.op,
+                size: self.types.size_of(value.ty),
+            },
+            false => GraphOpKind::Store {
+                pointer,
+                value: value.op,
+            },
+        };
+
+        let op = GraphOp::new(kind, value.ty, id);
+        self.append.ops.push(op);
+    }
+
+    fn write_var(&mut self, id: ExprId, var: VariableInfo, value: Value) {
+        if value.ty.is_aggregate() {
+            let pointer = self.var_address(id, var);
+            self.store(id, pointer, value);
+
+            return;
+        }
+
+        let kind = GraphOpKind::Mov {
+            target: Operand::StackLocal { id: var.id },
+            source: value.op,
+        };
+        let op = GraphOp::new(kind, value.ty, id);
+        self.append.ops.push(op);
+    }
+
+    fn var_address(&mut self, id: ExprId, var: VariableInfo) -> Operand {
+        let ty = self.types.pointer_to(var.ty);
+        let target = self.register_id();
+
+        let kind = GraphOpKind::StackAddress { target, id: var.id };
+        let op = GraphOp::new(kind, ty, id);
+        self.append.ops.push(op);
+
+        return target;
+    }
+
+    fn declare_result_slot(&mut self, id: ExprId) -> ResultSlot {
+        let slot = ResultSlot {
+            id: self.reserve_var_id(),
+            block_id: self.append.block_id,
+            index: self.append.ops.len(),
+        };
+
+        let kind = GraphOpKind::DeclareStack { size: 0 };
+        let op = GraphOp::new(kind, Type::Null, id);
+        self.append.ops.push(op);
+
+        return slot;
+    }
+
+    fn save_to_slot(&mut self, id: ExprId, slot: ResultSlot, value: Value) -> Value {
+        let var = VariableInfo {
+            id: slot.id,
+            ty: value.ty,
+        };
+        let pointer = self.var_address(id, var);
+        self.store(id, pointer, value);
+
+        return Value::new(pointer, value.ty);
+    }
+
+    // The slot only needs space if something got saved to it, and by now
+    // its DeclareStack op might be in a block that's already in the graph
+    fn finish_result_slot(&mut self, slot: ResultSlot, saved: Type) {
+        let size = match saved.is_aggregate() {
+            true => self.types.stack_size(saved),
+            false => 0,
+        };
+
+        let op = match self.append.block_id == slot.block_id {
+            true => &mut self.append.ops[slot.index],
+            false => {
+                let start = self.graph.blocks[slot.block_id as usize].ops.start;
+                &mut self.graph.ops[start as usize + slot.index]
+            }
+        };
+
+        op.kind = GraphOpKind::DeclareStack { size };
+    }
+
+    // `alloc(T, n)` makes space on the heap for `n` values of type `T`, and
+    // returns a `*T` pointing to the first one
+    fn check_alloc(&mut self, id: ExprId, args: ExprRange) -> Result<Value, Error> {
+        let mut args = args.into_iter();
+        let (ty, count) = match (args.next(), args.next(), args.next()) {
+            (Some(ty), Some(count), None) => (ty, count),
+            _ => {
+                let message = "alloc takes a type and a count, like alloc(u8, 10)";
+                return Err(Error::new(message, id.loc()));
+            }
+        };
+
+        let pointee = self.check_type(ty)?;
+        let ty = self.types.pointer_to(pointee);
+
+        let count_value = self.check_expr_as(count, Type::U64)?;
+        if !count_value.ty.is_integer() {
+            let message = "alloc count should be an integer";
+            return Err(Error::new(message, count.loc()));
+        }
+
+        let wide = self.register_id();
+        let kind = GraphOpKind::Cast {
+            target: wide,
+            value: count_value.op,
+            from: count_value.ty,
+        };
+        let op = GraphOp::new(kind, Type::U64, count);
+        self.append.ops.push(op);
+
+        let size = self.register_id();
+        let kind = GraphOpKind::ConstantU64 {
+            target: size,
+            value: self.types.size_of(pointee) as u64,
+        };
+        let op = GraphOp::new(kind, Type::U64, id);
+        self.append.ops.push(op);
+
+        let bytes = self.register_id();
+        let kind = GraphOpKind::Mul {
+            target: bytes,
+            left: wide,
+            right: size,
+        };
+        let op = GraphOp::new(kind, Type::U64, id);
+        self.append.ops.push(op);
+
+        let target = self.register_id();
+        let kind = GraphOpKind::HeapAlloc {
+            target,
+            size: bytes,
+        };
+        let op = GraphOp::new(kind, ty, id);
+        self.append.ops.push(op);
+
+        return Ok(Value::new(target, ty));
+    }
+
+    fn check_free(&mut self, id: ExprId, args: ExprRange) -> Result<Value, Error> {
+        let mut args = args.into_iter();
+        let pointer = match (args.next(), args.next()) {
+            (Some(pointer), None) => pointer,
+            _ => {
+                return Err(Error::new("free takes exactly one pointer", id.loc()));
+            }
+        };
+
+        let value = self.check_expr(ValueSlot::SaveSomewhere, pointer)?;
+        if self.types.pointee(value.ty).is_none() {
+            return Err(Error::new("can only free pointers", pointer.loc()));
+        }
+
+        let kind = GraphOpKind::HeapDealloc { pointer: value.op };
+        let op = GraphOp::new(kind, Type::Null, id);
+        self.append.ops.push(op);
+
+        return Ok(NULL);
+    }
+
+    // Casts look like calls, e.g. `s16(x)`
+    fn check_cast(&mut self, id: ExprId, ty: Type, args: ExprRange) -> Result<Value, Error> {
+        if args.len() != 1 {
+            return Err(Error::new("casts take exactly one value", id.loc()));
+        }
+
+        let castable = |ty: Type| {
+            return ty.is_integer() || ty.is_float() || ty == Type::Bool || ty == Type::Char;
+        };
+
+        if !castable(ty) {
+            let message = "can only cast to numbers, bools and chars";
+            return Err(Error::new(message, id.loc()));
+        }
+
+        let arg = match args.into_iter().next() {
+            Some(arg) => arg,
+            None => unreachable!(),
+        };
+
+        let value = self.check_expr(ValueSlot::SaveSomewhere, arg)?;
+
+        if !castable(value.ty) {
+            let message = "can only cast numbers, bools and chars";
+            return Err(Error::new(message, arg.loc()));
+        }
+
+        if value.ty.is_float() && ty == Type::Bool {
+            return Err(Error::new("can't cast floats to bool", id.loc()));
+        }
+
+        // Chars are code points, so they only really make sense as integers
+        let (from_char, to_char) = (value.ty == Type::Char, ty == Type::Char);
+        let char_ok = |ty: Type| ty.is_integer() || ty == Type::Char;
+        if (from_char && !char_ok(ty)) || (to_char && !char_ok(value.ty)) {
+            let message = "chars can only be cast to and from integers";
+            return Err(Error::new(message, id.loc()));
+        }
+
+        let target = self.register_id();
+
+        let kind = GraphOpKind::Cast {
+            target,
+            value: value.op,
+            from: value.ty,
+        };
+        let op = GraphOp::new(kind, ty, id);
+        self.append.ops.push(op);
+
+        return Ok(Value::new(target, ty));
+    }
+
+    fn check_call(&mut self, id: ExprId, symbol: u32, args: ExprRange) -> Result<Value, Error> {
+        let callee = match *id {
+            ExprKind::Call { callee, .. } => callee,
+            _ => unreachable!(),
+        };
+
+        let info = match self.search_proc(symbol) {
+            Some(info) => info,
+            None => {
+                return Err(Error::new("couldn't find procedure", callee.loc()));
+            }
+        };
+
+        if args.len() != info.params.len() {
+            let message = format!(
+                "procedure takes {} arguments but {} were given",
+                info.params.len(),
+                args.len()
+            );
+
+            return Err(Error::new(message, id.loc()));
+        }
+
+        if args.len() > u8::MAX as usize {
+            return Err(Error::new("too many arguments", id.loc()));
+        }
+
+        let mut values = Pod::new();
+        for (arg, param) in args.into_iter().zip(info.params) {
+            let expected = match *param {
+                ExprKind::Param { ty, .. } => self.check_type(ty)?,
+                _ => unreachable!(),
+            };
+
+            let value = self.check_expr_as(arg, expected)?;
+            if value.ty != expected {
+                return Err(Error::new(
+                    "argument type doesn't match the procedure's parameter type",
+                    arg.loc(),
+                ));
+            }
+
+            values.push((arg, value));
+        }
+
+        // Large return values get written to temporary stack space, which the
+        // callee gets a pointer to in register 0. It has to be declared before
+        // the arguments, which get handed off to the callee.
+        let target = match info.return_type {
+            Type::Null => Operand::Null,
+            ty if ty.is_aggregate() => {
+                let var_id = self.reserve_var_id();
+                let var = VariableInfo { id: var_id, ty };
+
+                let size = self.types.stack_size(ty);
+                let kind = GraphOpKind::DeclareStack { size };
+                let op = GraphOp::new(kind, ty, id);
+                self.append.ops.push(op);
+
+                self.var_address(id, var)
+            }
+            _ => self.register_id(),
+        };
+
+        // Arguments get moved onto the stack right before the call, so that
+        // the argument expressions can't mess up the stack IDs. The callee owns
+        // them afterwards, so they aren't counted as part of this scope.
+        for index in 0..values.len() {
+            let (arg, value) = values[index];
+            let var_id = self.ids.next_variable_id + index as u16;
+
+            let size = self.types.stack_size(value.ty);
+            let kind = GraphOpKind::DeclareStack { size };
+            let op = GraphOp::new(kind, value.ty, arg);
+            self.append.ops.push(op);
+
+            let var = VariableInfo {
+                id: var_id,
+                ty: value.ty,
+            };
+            self.write_var(arg, var, value);
+        }
+
+        let kind = GraphOpKind::Call {
+            target,
+            block: info.block_id,
+            arg_count: values.len() as u16,
+        };
+        let op = GraphOp::new(kind, info.return_type, id);
+        self.append.ops.push(op);
+
+        return Ok(Value::new(target, info.return_type));
+    }
+
+    fn check_type(&mut self, id: ExprId) -> Result<Type, Error> {
+        if let ExprKind::Ident { symbol } = *id {
+            if let Some(ty) = builtin_type(symbol) {
+                return Ok(ty);
+            }
+
+            if let Some(ty) = self.search_type(symbol) {
+                return Ok(ty);
+            }
+        }
+
+        // Types can also show up where expressions are expected, like in
+        // `alloc(*u8, 10)`, so `*T` might be parsed as a dereference
+        match *id {
+            ExprKind::PointerType { pointee }
+            | ExprKind::UnaryOp {
+                kind: UnaryExprKind::Deref,
+                value: pointee,
+            } => {
+                let pointee = self.check_type(pointee)?;
+
+                return Ok(self.types.pointer_to(pointee));
+            }
+
+            ExprKind::ArrayType { len, elem: elem_id } => {
+                let elem = self.check_type(elem_id)?;
+                self.require_layout(elem_id, elem)?;
+
+                let len = match literal_value(len) {
+                    Some((value, _)) if value >= 0 && value <= u32::MAX as i128 => value as u32,
+                    _ => {
+                        let message = "array length should be a non-negative integer literal";
+                        return Err(Error::new(message, len.loc()));
+                    }
+                };
+
+                // Arrays live on the stack, and stack variables can only be
+                // so big
+                let size = self.types.size_of(elem) as u64 * len as u64;
+                if size > u16::MAX as u64 {
+                    return Err(Error::new("array is too large", id.loc()));
+                }
+
+                return Ok(self.types.array_of(elem, len));
+            }
+
+            ExprKind::SliceType { elem } => {
+                let elem = self.check_type(elem)?;
+
+                return Ok(self.types.slice_of(elem));
+            }
+
+            _ => {}
+        }
+
+        return Err(Error::new("unrecognized type", id.loc()));
+    }
+
+    // Completes the current block properly, and also completes all the blocks
+    // it produces by having them jump to the exit block. The current block
+    // should already end with a branch to each of the arms.
+    //
+    // When there's a result slot, each arm's value gets checked as `expected`
+    // and moved into the same register, which is the value of the whole
+    // thing. Arms that are untyped literals get checked last, so that they
+    // can take on the type of the other arms.
+    fn check_arms(
+        &mut self,
+        exit_block: u32,
+        arms: &[Arm],
+        expected: Type,
+        result_slot: Option<ResultSlot>,
+    ) -> Result<Value, Error> {
+        let mut ty = Type::Never;
+        let mut saved = Type::Null;
+
+        let mut arms = arms.to_vec();
+        arms.sort_by_key(|arm| untyped_literal(arm.expr));
+
+        // Only one arm runs, so they can all use the same registers. Every
+        // arm writes its value to the target as its last step, so they can
+        // start from the target's own register too, which keeps deeply
+        // nested if-else chains from running out of registers.
+        let next_op_id = self.ids.next_op_id;
+        let target = match result_slot {
+            Some(_) => self.register_id(),
+            None => Operand::Null,
+        };
+        let after_target = self.ids.next_op_id;
+
+        for arm in arms {
+            self.ids.next_op_id = next_op_id;
+
+            let expected = match ty {
+                Type::Null | Type::Never => expected,
+                ty => ty,
+            };
+
+            let mut append = GraphAppend {
+                block_id: arm.block_id,
+                ops: Pod::new(),
+            };
+
+            let var_base = self.ids.next_variable_id;
+            let mut branch = self.chain_branch(&mut append);
+
+            if !arm.pattern.is_null() {
+                branch.bind_pattern(arm.pattern, arm.value)?;
+            }
+
+            let mut value = match result_slot {
+                Some(_) => branch.check_expr_as(arm.expr, expected)?,
+                None => branch.check_expr(ValueSlot::Delete, arm.expr)?,
+            };
+
+            // Same goes for stack space, which gets cleaned up at the end of
+            // each arm
+            let count = branch.ids.next_variable_id - var_base;
+            branch.ids.next_variable_id = var_base;
+
+            if let Some(result_slot) = result_slot {
+                if value.ty.is_aggregate() && count > 0 {
+                    value = branch.save_to_slot(arm.expr, result_slot, value);
+                    saved = value.ty;
+                }
+            }
+
+            if value.ty != Type::Never {
+                if result_slot.is_some() && value.ty != Type::Null {
+                    let kind = GraphOpKind::Mov {
+                        target,
+                        source: value.op,
+                    };
+                    let op = GraphOp::new(kind, value.ty, arm.expr);
+                    branch.append.ops.push(op);
+                }
+
+                if count > 0 {
+                    let kind = GraphOpKind::StackDealloc { count };
+                    let op = GraphOp::new(kind, Type::Null, arm.expr);
+                    branch.append.ops.push(op);
+                }
+
+                let kind = GraphOpKind::Jump { block: exit_block };
+                let op = GraphOp::new(kind, Type::Null, arm.expr);
+                branch.append.ops.push(op);
+            }
 
-                let kind = GraphOpKind::Add {
-                    target,
-                    left: left_value.op,
-                    right: right_value.op,
-                };
-                let op = GraphOp::new(kind, left_value.ty, id);
-                self.append.ops.push(op);
+            core::mem::drop(branch);
 
-                return Ok(Value::new(target, left_value.ty));
+            self.graph.write_block(append.block_id, append.ops);
+
+            // Arms that never finish don't have any say in the resulting type
+            if value.ty == Type::Never {
+                continue;
             }
 
-            Block(block) => {
-                let mut child = self.chain_local();
+            if result_slot.is_none() {
+                ty = Type::Null;
+                continue;
+            }
 
-                for expr in block.stmts {
-                    child.check_expr(ValueSlot::Delete, expr)?;
-                }
+            if ty != Type::Never && ty != value.ty {
+                return Err(Error::new(
+                    "the branches of this expression have different types",
+                    arm.expr.loc(),
+                ));
+            }
 
-                let count = child.scope.vars.len() as u16;
-                let kind = GraphOpKind::StackDealloc { count };
-                let op = GraphOp::new(kind, Type::Null, id);
-                self.append.ops.push(op);
+            ty = value.ty;
+        }
 
-                return Ok(NULL);
-            }
+        if let Some(result_slot) = result_slot {
+            self.finish_result_slot(result_slot, saved);
+        }
 
-            Call { callee, args } => {
-                const PRINT: u32 = Key::Print as u32;
+        self.ids.next_op_id = after_target;
+
+        self.replace_block(GraphAppend {
+            block_id: exit_block,
+            ops: Pod::new(),
+        });
 
-                match *callee {
-                    Ident { symbol: PRINT } => {}
+        return match ty {
+            Type::Null | Type::Never => Ok(Value::new(Operand::Null, ty)),
+            _ => Ok(Value::new(target, ty)),
+        };
+    }
 
-                    _ => {
-                        return Err(Error::new(
-                            "function calls besides print aren't implemented",
-                            callee.loc(),
-                        ));
-                    }
-                }
+    // Checks the loop body, which should go in the current block. Returns
+    // whether or not the loop has a break in it.
+    fn check_loop_body(
+        &mut self,
+        label: ExprId,
+        body: ExprId,
+        continue_block: u32,
+        break_block: u32,
+    ) -> Result<bool, Error> {
+        let label = label_symbol(label);
 
-                for arg in args {
-                    let value = self.check_expr(ValueSlot::SaveSomewhere, arg)?;
+        let info = LoopInfo {
+            label,
+            break_block,
+            continue_block,
+            var_base: self.ids.next_variable_id,
+            broken: Cell::new(false),
+        };
 
-                    let kind = GraphOpKind::Print { value: value.op };
-                    let op = GraphOp::new(kind, value.ty, arg);
-                    self.append.ops.push(op);
-                }
+        let mut child = self.chain_loop(info);
 
-                let op = GraphOp::new(GraphOpKind::PrintNewline, Type::Null, id);
-                self.append.ops.push(op);
+        let ty = child.check_stmt(body)?;
 
-                return Ok(NULL);
-            }
+        if ty != Type::Never {
+            let kind = GraphOpKind::Jump {
+                block: continue_block,
+            };
+            let op = GraphOp::new(kind, Type::Null, body);
+            child.append.ops.push(op);
+        }
 
-            k => unimplemented!("{}", k.name()),
+        let broken = match &child.scope.kind {
+            ScopeKind::Loop { info, .. } => info.broken.get(),
+            _ => unreachable!(),
+        };
+
+        return Ok(broken);
+    }
+
+    // Conditions can get run many times, like in loops, so any temporary stack
+    // space they use gets cleaned up right away
+    fn check_cond(&mut self, cond: ExprId) -> Result<Value, Error> {
+        let var_base = self.ids.next_variable_id;
+        let value = self.check_expr(ValueSlot::SaveSomewhere, cond)?;
+        self.free_temps(var_base, cond);
+
+        if !value.ty.is_integer() && value.ty != Type::Bool {
+            return Err(Error::new(
+                "condition should be a bool or a number",
+                cond.loc(),
+            ));
         }
+
+        return Ok(value);
     }
 
-    // Completes the current block properly, and also completes all the blocks
-    // it produces by having them jump to the exit block
-    fn check_arms(
+    // `a && b` and `a || b` only evaluate `b` if they need to, so they get
+    // lowered into branches that write a constant to the result
+    fn check_short_circuit(
         &mut self,
-        var_target: u16,
-        exit_block: u32,
-        arms: &[Arm],
+        id: ExprId,
+        kind: BinaryExprKind,
+        left: ExprId,
+        right: ExprId,
     ) -> Result<Value, Error> {
-        for &arm in arms {
-            let mut append = GraphAppend {
-                block_id: arm.block_id,
+        let target = self.register_id();
+
+        let right_block = self.graph.get_block_id();
+        let true_block = self.graph.get_block_id();
+        let false_block = self.graph.get_block_id();
+        let end_block = self.graph.get_block_id();
+
+        let left_value = self.check_cond(left)?;
+
+        let (if_true, if_false) = match kind {
+            BinaryExprKind::BoolAnd => (right_block, false_block),
+            _ => (true_block, right_block),
+        };
+
+        let kind = GraphOpKind::Branch {
+            cond: left_value.op,
+            if_true,
+            if_false,
+        };
+        let op = GraphOp::new(kind, Type::Null, left);
+        self.append.ops.push(op);
+
+        self.replace_block(GraphAppend {
+            block_id: right_block,
+            ops: Pod::new(),
+        });
+
+        let right_value = self.check_cond(right)?;
+
+        let kind = GraphOpKind::Branch {
+            cond: right_value.op,
+            if_true: true_block,
+            if_false: false_block,
+        };
+        let op = GraphOp::new(kind, Type::Null, right);
+        self.append.ops.push(op);
+
+        for (block_id, value) in [(true_block, 1), (false_block, 0)] {
+            self.replace_block(GraphAppend {
+                block_id,
                 ops: Pod::new(),
-            };
+            });
 
-            let mut branch = self.chain_branch(&mut append);
-            branch.check_expr(ValueSlot::SaveSomewhere, arm.expr)?;
+            let kind = GraphOpKind::ConstantU64 { target, value };
+            let op = GraphOp::new(kind, Type::Bool, id);
+            self.append.ops.push(op);
+
+            let kind = GraphOpKind::Jump { block: end_block };
+            let op = GraphOp::new(kind, Type::Null, id);
+            self.append.ops.push(op);
         }
 
-        return Ok(NULL);
+        self.replace_block(GraphAppend {
+            block_id: end_block,
+            ops: Pod::new(),
+        });
+
+        return Ok(Value::new(target, Type::Bool));
     }
 
     fn chain_local<'b>(&'b mut self) -> CheckEnv<'b> {
         return CheckEnv {
             types: self.types,
+            table: self.table,
             graph: self.graph,
             ids: self.ids,
             append: self.append,
@@ -338,21 +3635,34 @@ impl<'a> CheckEnv<'a> {
                     parent: &mut self.scope,
                 },
                 vars: HashMap::new(),
+                procs: HashMap::new(),
+                types: HashMap::new(),
+                defers: Vec::new(),
             },
         };
     }
 
-    fn chain_proc<'b>(&'b mut self, ids: &'b mut IdTracker) -> CheckEnv<'b> {
+    fn chain_proc<'b>(
+        &'b mut self,
+        ids: &'b mut IdTracker,
+        append: &'b mut GraphAppend,
+        return_type: Type,
+    ) -> CheckEnv<'b> {
         return CheckEnv {
             types: self.types,
+            table: self.table,
             graph: self.graph,
             ids,
-            append: self.append,
+            append,
             scope: ScopeEnv {
                 kind: ScopeKind::Procedure {
                     parent: &mut self.scope,
+                    return_type,
                 },
                 vars: HashMap::new(),
+                procs: HashMap::new(),
+                types: HashMap::new(),
+                defers: Vec::new(),
             },
         };
     }
@@ -360,6 +3670,7 @@ impl<'a> CheckEnv<'a> {
     fn chain_branch<'b>(&'b mut self, append: &'b mut GraphAppend) -> CheckEnv<'b> {
         return CheckEnv {
             types: self.types,
+            table: self.table,
             graph: self.graph,
             ids: self.ids,
             append,
@@ -368,6 +3679,29 @@ impl<'a> CheckEnv<'a> {
                     parent: &mut self.scope,
                 },
                 vars: HashMap::new(),
+                procs: HashMap::new(),
+                types: HashMap::new(),
+                defers: Vec::new(),
+            },
+        };
+    }
+
+    fn chain_loop<'b>(&'b mut self, info: LoopInfo) -> CheckEnv<'b> {
+        return CheckEnv {
+            types: self.types,
+            table: self.table,
+            graph: self.graph,
+            ids: self.ids,
+            append: self.append,
+            scope: ScopeEnv {
+                kind: ScopeKind::Loop {
+                    parent: &mut self.scope,
+                    info,
+                },
+                vars: HashMap::new(),
+                procs: HashMap::new(),
+                types: HashMap::new(),
+                defers: Vec::new(),
             },
         };
     }
@@ -386,6 +3720,8 @@ impl<'a> CheckEnv<'a> {
         self.graph.write_block(append.block_id, append.ops);
     }
 
+    // Variables from outside the current procedure live in a different stack
+    // frame, so the search stops at procedure boundaries
     fn search(&self, symbol: u32) -> Option<VariableInfo> {
         let mut current = &self.scope;
 
@@ -394,6 +3730,153 @@ impl<'a> CheckEnv<'a> {
                 return Some(*e);
             }
 
+            if let ScopeKind::Procedure { .. } = current.kind {
+                return None;
+            }
+
+            if let Some(parent) = current.parent() {
+                current = parent;
+
+                continue;
+            }
+
+            return None;
+        }
+    }
+
+    fn search_proc(&self, symbol: u32) -> Option<ProcInfo> {
+        let mut current = &self.scope;
+
+        loop {
+            if let Some(e) = current.procs.get(&symbol) {
+                return Some(*e);
+            }
+
+            if let Some(parent) = current.parent() {
+                current = parent;
+
+                continue;
+            }
+
+            return None;
+        }
+    }
+
+    fn search_type(&self, symbol: u32) -> Option<Type> {
+        let mut current = &self.scope;
+
+        loop {
+            if let Some(e) = current.types.get(&symbol) {
+                return Some(*e);
+            }
+
+            if let Some(parent) = current.parent() {
+                current = parent;
+
+                continue;
+            }
+
+            return None;
+        }
+    }
+
+    // Finds the innermost loop with the given label, or just the innermost
+    // loop if there's no label, along with how many scopes deep it is. Loops
+    // outside of the current procedure don't count.
+    fn loop_info(&self, label: Option<u32>) -> Option<(usize, &LoopInfo)> {
+        let mut current = &self.scope;
+        let mut depth = 0;
+
+        loop {
+            match &current.kind {
+                ScopeKind::Loop { info, .. } if label.is_none() || info.label == label => {
+                    return Some((depth, info));
+                }
+                ScopeKind::Procedure { .. } => return None,
+                _ => {}
+            }
+
+            if let Some(parent) = current.parent() {
+                current = parent;
+                depth += 1;
+
+                continue;
+            }
+
+            return None;
+        }
+    }
+
+    // How many scopes deep the current procedure's scope is
+    fn proc_depth(&self) -> usize {
+        let mut current = &self.scope;
+        let mut depth = 0;
+
+        while let Some(parent) = current.parent() {
+            if let ScopeKind::Procedure { .. } = current.kind {
+                break;
+            }
+
+            current = parent;
+            depth += 1;
+        }
+
+        return depth;
+    }
+
+    // Emits the deferred code for the innermost `depth` scopes, innermost
+    // first. Each defer is checked with the variables that were visible
+    // where it was declared, so later declarations don't leak into it.
+    fn emit_defers(&mut self, depth: usize) -> Result<(), Error> {
+        let mut current = &self.scope;
+
+        for _ in 0..depth {
+            for defer in current.defers.iter().rev() {
+                let kind = match current.parent() {
+                    Some(parent) => ScopeKind::Local { parent },
+                    None => ScopeKind::Global {},
+                };
+
+                let mut env = CheckEnv {
+                    types: self.types,
+                    table: self.table,
+                    graph: self.graph,
+                    ids: self.ids,
+                    append: self.append,
+                    scope: ScopeEnv {
+                        kind,
+                        vars: defer.vars.clone(),
+                        procs: current.procs.clone(),
+                        types: current.types.clone(),
+                        defers: Vec::new(),
+                    },
+                };
+
+                if env.check_stmt(defer.expr)? == Type::Never {
+                    return Err(Error::new(
+                        "deferred code can't jump out of its scope",
+                        defer.expr.loc(),
+                    ));
+                }
+            }
+
+            current = match current.parent() {
+                Some(parent) => parent,
+                None => break,
+            };
+        }
+
+        return Ok(());
+    }
+
+    fn return_type(&self) -> Option<Type> {
+        let mut current = &self.scope;
+
+        loop {
+            if let ScopeKind::Procedure { return_type, .. } = current.kind {
+                return Some(return_type);
+            }
+
             if let Some(parent) = current.parent() {
                 current = parent;
 
@@ -404,6 +3887,40 @@ impl<'a> CheckEnv<'a> {
         }
     }
 
+    fn declare_proc(&mut self, id: ExprId, symbol: u32, info: ProcInfo) -> Result<(), Error> {
+        use std::collections::hash_map::Entry;
+
+        let e = match self.scope.procs.entry(symbol) {
+            Entry::Vacant(v) => v,
+            Entry::Occupied(o) => {
+                return Err(Error::new("redeclared procedure", id.loc()));
+            }
+        };
+
+        e.insert(info);
+
+        return Ok(());
+    }
+
+    fn declare_type(&mut self, id: ExprId, symbol: u32, ty: Type) -> Result<(), Error> {
+        use std::collections::hash_map::Entry;
+
+        if builtin_type(symbol).is_some() {
+            return Err(Error::new("redeclared type", id.loc()));
+        }
+
+        let e = match self.scope.types.entry(symbol) {
+            Entry::Vacant(v) => v,
+            Entry::Occupied(o) => {
+                return Err(Error::new("redeclared type", id.loc()));
+            }
+        };
+
+        e.insert(ty);
+
+        return Ok(());
+    }
+
     fn declare(&mut self, id: ExprId, symbol: u32, info: VariableInfo) -> Result<(), Error> {
         use std::collections::hash_map::Entry;
 
@@ -426,6 +3943,21 @@ impl<'a> CheckEnv<'a> {
         return id;
     }
 
+    // Registers at or after `base` are done with, except for the ones
+    // holding `values`
+    fn release_registers(&mut self, base: u16, values: &[Value]) {
+        let mut next_op_id = base;
+        for value in values {
+            if let Operand::RegisterValue { id } = value.op {
+                if id >= base {
+                    next_op_id = next_op_id.max(id + 1);
+                }
+            }
+        }
+
+        self.ids.next_op_id = next_op_id;
+    }
+
     fn register_id(&mut self) -> Operand {
         let id = self.ids.next_op_id;
         self.ids.next_op_id += 1;
@@ -438,12 +3970,140 @@ impl<'a> CheckEnv<'a> {
 struct Arm {
     block_id: u32,
     expr: ExprId,
+
+    // For match arms, the names in the pattern get bound to parts of the
+    // value. Null for the arms of an if.
+    pattern: ExprId,
+    value: Value,
+}
+
+// What a match arm checks for. Names and `_` match anything.
+#[derive(Clone)]
+enum Pattern {
+    Any,
+    Variant { index: u32, payload: Vec<Pattern> },
 }
 
 enum ScopeKind<'a> {
     Global {},
-    Procedure { parent: &'a ScopeEnv<'a> },
-    Local { parent: &'a ScopeEnv<'a> },
+    Procedure {
+        parent: &'a ScopeEnv<'a>,
+        return_type: Type,
+    },
+    Local {
+        parent: &'a ScopeEnv<'a>,
+    },
+    Loop {
+        parent: &'a ScopeEnv<'a>,
+        info: LoopInfo,
+    },
+}
+
+fn builtin_type(symbol: u32) -> Option<Type> {
+    const U8: u32 = Key::U8 as u32;
+    const U16: u32 = Key::U16 as u32;
+    const U32: u32 = Key::U32 as u32;
+    const U64: u32 = Key::U64 as u32;
+    const S8: u32 = Key::S8 as u32;
+    const S16: u32 = Key::S16 as u32;
+    const S32: u32 = Key::S32 as u32;
+    const S64: u32 = Key::S64 as u32;
+    const BOOL: u32 = Key::Bool as u32;
+    const F32: u32 = Key::F32 as u32;
+    const F64: u32 = Key::F64 as u32;
+    const CHAR: u32 = Key::Char as u32;
+    const STRING: u32 = Key::String as u32;
+
+    let ty = match symbol {
+        U8 => Type::U8,
+        U16 => Type::U16,
+        U32 => Type::U32,
+        U64 => Type::U64,
+        S8 => Type::S8,
+        S16 => Type::S16,
+        S32 => Type::S32,
+        S64 => Type::S64,
+        BOOL => Type::Bool,
+        F32 => Type::F32,
+        F64 => Type::F64,
+        CHAR => Type::Char,
+        STRING => Type::String,
+        _ => return None,
+    };
+
+    return Some(ty);
+}
+
+// The value of an integer literal, including negative ones like `-1`
+// along with its suffix
+fn literal_value(id: ExprId) -> Option<(i128, ExprId)> {
+    return match *id {
+        ExprKind::Integer { value, suffix } => Some((value as i128, suffix)),
+        ExprKind::UnaryOp {
+            kind: UnaryExprKind::Negate,
+            value,
+        } => match *value {
+            ExprKind::Integer { value, suffix } => Some((-(value as i128), suffix)),
+            _ => None,
+        },
+        _ => None,
+    };
+}
+
+// How picky an untyped literal is about its type; 0 for everything that
+// isn't one, 1 for float literals, and 2 for integer literals, which can be
+// floats too
+fn untyped_literal(id: ExprId) -> u8 {
+    if matches!(float_value(id), Some((_, suffix)) if suffix.is_null()) {
+        return 1;
+    }
+
+    if matches!(literal_value(id), Some((_, suffix)) if suffix.is_null()) {
+        return 2;
+    }
+
+    return 0;
+}
+
+// Same as literal_value, but for float literals like `-1.5`
+fn float_value(id: ExprId) -> Option<(f64, ExprId)> {
+    return match *id {
+        ExprKind::Float { value, suffix } => Some((value, suffix)),
+        ExprKind::UnaryOp {
+            kind: UnaryExprKind::Negate,
+            value,
+        } => match *value {
+            ExprKind::Float { value, suffix } => Some((-value, suffix)),
+            _ => None,
+        },
+        _ => None,
+    };
+}
+
+fn align_up(value: u32, align: u32) -> u32 {
+    return value.div_ceil(align) * align;
+}
+
+fn label_symbol(label: ExprId) -> Option<u32> {
+    if label.is_null() {
+        return None;
+    }
+
+    return match *label {
+        ExprKind::Ident { symbol } => Some(symbol),
+        _ => unreachable!(),
+    };
+}
+
+struct LoopInfo {
+    label: Option<u32>,
+    break_block: u32,
+    continue_block: u32,
+
+    // next_variable_id at the start of the loop body
+    var_base: u16,
+
+    broken: Cell<bool>,
 }
 
 #[derive(Clone, Copy)]
@@ -452,9 +4112,26 @@ struct VariableInfo {
     ty: Type,
 }
 
+#[derive(Clone, Copy)]
+struct ProcInfo {
+    block_id: u32,
+    params: ExprRange,
+    return_type: Type,
+}
+
 struct ScopeEnv<'a> {
     kind: ScopeKind<'a>,
     vars: HashMap<u32, VariableInfo>,
+    procs: HashMap<u32, ProcInfo>,
+    types: HashMap<u32, Type>,
+    defers: Vec<DeferInfo>,
+}
+
+struct DeferInfo {
+    expr: ExprId,
+
+    // The variables in scope when the defer was declared
+    vars: HashMap<u32, VariableInfo>,
 }
 
 impl<'a> ScopeEnv<'a> {
@@ -463,6 +4140,7 @@ impl<'a> ScopeEnv<'a> {
             ScopeKind::Global { .. } => None,
             ScopeKind::Procedure { parent, .. } => Some(parent),
             ScopeKind::Local { parent } => Some(parent),
+            ScopeKind::Loop { parent, .. } => Some(parent),
         };
     }
 }
@@ -3,7 +3,7 @@ use core::cell::*;
 use core::mem::*;
 use core::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum Type {
     // Means that the expression that returns this value doesn't ever return
     // a value directly (early return, loop forever, crash, ...)
@@ -12,12 +12,119 @@ pub enum Type {
     // Void in C
     Null,
 
+    U8,
+    U16,
+    U32,
     U64,
+    S8,
+    S16,
+    S32,
+    S64,
+    Bool,
+    F32,
+    F64,
+    Char,
+    // A string is a pointer and a length, which live in a 16 byte header: a
+    // pointer to the string's bytes, and then the number of bytes as a u64.
+    // Values of this type are pointers to the header. Headers are never
+    // written to once they're made, so copying the pointer copies the string.
     String,
 
+    // Index into the checker's TypeEnv, which has the type being pointed to
+    Pointer(u16),
+    // Indices into the checker's TypeEnv, which has the element type (and the
+    // length, for arrays)
+    Array(u16),
+    Slice(u16),
+    // Index into the checker's TypeEnv struct list, which has the fields
+    Struct(u16),
+    // Index into the checker's TypeEnv enum list; values are the index of
+    // the variant
+    Enum(u16),
+    // Same as Enum, but for enums where some variants have a payload. These
+    // are a u32 variant index followed by the payload.
+    Union(u16),
+
     Procedure,
 }
 
+impl Type {
+    pub fn is_integer(self) -> bool {
+        use Type::*;
+
+        return matches!(self, U8 | U16 | U32 | U64 | S8 | S16 | S32 | S64);
+    }
+
+    pub fn is_float(self) -> bool {
+        return matches!(self, Type::F32 | Type::F64);
+    }
+
+    pub fn is_signed(self) -> bool {
+        use Type::*;
+
+        return matches!(self, S8 | S16 | S32 | S64);
+    }
+
+    // Arrays, slices, structs and enums with payloads don't fit in a register,
+    // so their values are pointers to wherever they're stored
+    pub fn is_aggregate(self) -> bool {
+        use Type::*;
+
+        return matches!(self, Array(_) | Slice(_) | Struct(_) | Union(_));
+    }
+
+    // Size in bytes of values of this type, for types that fit in a register
+    pub fn size(self) -> u32 {
+        use Type::*;
+
+        return match self {
+            U8 | S8 | Bool => 1,
+            U16 | S16 => 2,
+            U32 | S32 | F32 | Char | Enum(_) => 4,
+            _ => 8,
+        };
+    }
+
+    // Range of values an integer type can hold, inclusive
+    pub fn int_range(self) -> (i128, i128) {
+        let bits = self.size() * 8;
+
+        if self.is_signed() {
+            return (-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1);
+        }
+
+        return (0, (1i128 << bits) - 1);
+    }
+
+    pub fn name(self) -> &'static str {
+        use Type::*;
+
+        return match self {
+            Never => "never",
+            Null => "null",
+            U8 => "u8",
+            U16 => "u16",
+            U32 => "u32",
+            U64 => "u64",
+            S8 => "s8",
+            S16 => "s16",
+            S32 => "s32",
+            S64 => "s64",
+            Bool => "bool",
+            F32 => "f32",
+            F64 => "f64",
+            Char => "char",
+            String => "string",
+            Pointer(_) => "pointer",
+            Array(_) => "array",
+            Slice(_) => "slice",
+            Struct(_) => "struct",
+            Enum(_) | Union(_) => "enum",
+            Procedure => "procedure",
+        };
+    }
+}
+
 // register sized operands
 #[derive(Debug, Clone, Copy)]
 pub enum Operand {
@@ -47,18 +154,178 @@ pub enum GraphOpKind {
         target: Operand,
         value: u64,
     },
+    // symbol is the string's text in the StringTable
+    ConstantString {
+        target: Operand,
+        symbol: u32,
+    },
+
+    // Pointer to a stack variable; the op's type is the pointer type
+    StackAddress {
+        target: Operand,
+        id: u16,
+    },
+    // The op's type is the type being read or written
+    Load {
+        target: Operand,
+        pointer: Operand,
+    },
+    Store {
+        pointer: Operand,
+        value: Operand,
+    },
+    // size is a u64 number of bytes; the op's type is the pointer type
+    HeapAlloc {
+        target: Operand,
+        size: Operand,
+    },
+    HeapDealloc {
+        pointer: Operand,
+    },
+
+    // Copies `size` bytes from `source` to `dest`, which are both pointers
+    MemCopy {
+        dest: Operand,
+        source: Operand,
+        size: u32,
+    },
+
+    // Errors when `index` isn't less than `len`; both are s64
+    IndexCheck {
+        index: Operand,
+        len: Operand,
+    },
+    // Errors unless 0 <= start <= end <= len; all of them are s64
+    RangeCheck {
+        start: Operand,
+        end: Operand,
+        len: Operand,
+    },
+
+    // Moves the pointer by `offset` bytes, without leaving its allocation;
+    // offset is an s64
+    PtrOffset {
+        target: Operand,
+        pointer: Operand,
+        offset: Operand,
+    },
 
     Add {
         target: Operand,
         left: Operand,
         right: Operand,
     },
+    Sub {
+        target: Operand,
+        left: Operand,
+        right: Operand,
+    },
+    Mul {
+        target: Operand,
+        left: Operand,
+        right: Operand,
+    },
+    Div {
+        target: Operand,
+        left: Operand,
+        right: Operand,
+    },
+    Mod {
+        target: Operand,
+        left: Operand,
+        right: Operand,
+    },
+    LShift {
+        target: Operand,
+        left: Operand,
+        right: Operand,
+    },
+    RShift {
+        target: Operand,
+        left: Operand,
+        right: Operand,
+    },
+    BitAnd {
+        target: Operand,
+        left: Operand,
+        right: Operand,
+    },
+    BitOr {
+        target: Operand,
+        left: Operand,
+        right: Operand,
+    },
+    BitXor {
+        target: Operand,
+        left: Operand,
+        right: Operand,
+    },
+
+    BitNot {
+        target: Operand,
+        value: Operand,
+    },
+    // Converts between number types and bool; the op's type is the type
+    // that's being converted to, and `from` is the type of `value`
+    Cast {
+        target: Operand,
+        value: Operand,
+        from: Type,
+    },
+    BoolNot {
+        target: Operand,
+        value: Operand,
+    },
+
+    CompLt {
+        target: Operand,
+        left: Operand,
+        right: Operand,
+    },
+    CompLeq {
+        target: Operand,
+        left: Operand,
+        right: Operand,
+    },
+    CompEq {
+        target: Operand,
+        left: Operand,
+        right: Operand,
+    },
+    CompNeq {
+        target: Operand,
+        left: Operand,
+        right: Operand,
+    },
 
     Print {
         value: Operand,
     },
     PrintNewline,
 
+    // arg_count is the number of stack variables right before the call that
+    // get handed off to the callee
+    Call {
+        target: Operand,
+        block: u32,
+        arg_count: u16,
+    },
+    Return {
+        value: Operand,
+    },
+
+    // Control flow; these have to be the last op in their block, since blocks
+    // don't necessarily get laid out in any particular order.
+    Jump {
+        block: u32,
+    },
+    // Jump to `if_false` when `cond` is zero, and to `if_true` otherwise
+    Branch {
+        cond: Operand,
+        if_true: u32,
+        if_false: u32,
+    },
+
     ExitSuccess,
 }
 
@@ -1,118 +1,909 @@
 use crate::*;
 
+// Block offsets aren't known until every block has been laid out, so jumps get
+// a placeholder address, and a relocation that gets patched at the end.
+#[derive(Debug, Clone, Copy)]
+pub struct Relocation {
+    // index of the u32 address word that needs patching
+    pub index: u32,
+    pub block: u32,
+}
+
 pub struct Assembler {
+    // Static data, like string literals. The executable gets added to this at
+    // the end of assembly.
+    pub data: AllocTracker,
+    // pointers to the headers of string literals, by symbol
+    pub strings: HashMap<u32, Ptr>,
+
     pub exe_bytes: Pod<u32>,
     pub loc_bytes: Pod<ExprId>,
     pub current_expr: ExprId,
+
+    // byte offset from the start of the executable for each block
+    pub block_offsets: Pod<u32>,
+    pub relocations: Pod<Relocation>,
 }
 
 impl Assembler {
+    const UNPLACED: u32 = u32::MAX;
+
     pub fn new() -> Self {
         return Self {
+            data: AllocTracker::new(),
+            strings: HashMap::new(),
+
             exe_bytes: Pod::with_capacity(256),
             loc_bytes: Pod::with_capacity(256),
             current_expr: ExprId::NULL,
+
+            block_offsets: Pod::new(),
+            relocations: Pod::new(),
         };
     }
 
-    pub fn assemble(mut self, graph: &Graph, entry_block: u32) -> AllocTracker {
-        /*
-            use GraphOpKind::*;
+    pub fn assemble(
+        mut self,
+        table: &StringTable,
+        graph: &Graph,
+        entry_block: u32,
+    ) -> AllocTracker {
+        for _ in 0..graph.blocks.len() {
+            self.block_offsets.push(Self::UNPLACED);
+        }
 
-            let block = graph.blocks[entry_block];
-            let ops = &graph.ops[block.ops];
+        // The entry block goes first, so that execution starts at the beginning
+        // of the executable. Every other block gets laid out after it in
+        // whatever order they were allocated in.
+        self.assemble_block(table, graph, entry_block);
 
-            for &op in ops {
-                self.current_expr = op.expr;
+        for block_id in 0..(graph.blocks.len() as u32) {
+            if block_id == entry_block {
+                continue;
+            }
 
-                match op.kind {
-                    DeclareStack { size } => {
-                        let len = AllocLen::new(size as u32);
-                        self.push(Opcode::StackAlloc {
-                            len,
-                            save_address: Out64Reg::NULL,
-                        });
-                    }
+            self.assemble_block(table, graph, block_id);
+        }
 
-                    StackDealloc { count } => {
-                        self.push(Opcode::StackDealloc { count });
-                    }
+        // NOTE: iterating by index here because deref-ing an empty Pod
+        // produces an unaligned slice
+        for i in 0..self.relocations.len() {
+            let reloc = self.relocations[i];
+            let offset = self.block_offsets[reloc.block];
+            assert!(
+                offset != Self::UNPLACED,
+                "jumped to a block that doesn't exist"
+            );
+
+            self.exe_bytes[reloc.index] = offset;
+        }
+
+        self.data.alloc_exe(self.exe_bytes, Some(self.loc_bytes));
+
+        return self.data;
+    }
+
+    fn assemble_block(&mut self, table: &StringTable, graph: &Graph, block_id: u32) {
+        use GraphOpKind::*;
+
+        let block = graph.blocks[block_id];
+        let ops = &graph.ops[block.ops];
+
+        self.block_offsets[block_id] = self.exe_bytes.len() as u32 * 4;
+
+        for &op in ops {
+            self.current_expr = op.expr;
+            let op_ty = op.ty;
+
+            match op.kind {
+                DeclareStack { size } => {
+                    let len = AllocLen::new(size as u32);
+                    self.push(Opcode::StackAlloc {
+                        len,
+                        save_address: Out64Reg::NULL,
+                    });
+                }
+
+                StackDealloc { count } => {
+                    self.push(Opcode::StackDealloc { count });
+                }
+
+                ConstantU64 { target, value } => {
+                    let register = self.output_register(target, 30);
+
+                    self.push(Opcode::Make64 {
+                        register_out: Out64Reg::new(register),
+                        stack_slot: StackSlot::MEH,
+                    });
+
+                    self.push(value as u32);
+                    self.push((value >> 32) as u32);
+
+                    self.write_to_operand(target, RegSize64, register);
+                }
+
+                // String values point to a header, which is laid out like
+                // Type::String says
+                ConstantString { target, symbol } => {
+                    let pointer = self.string_header(table, symbol);
+                    let register = self.output_register(target, 30);
+
+                    self.push(Opcode::Make64 {
+                        register_out: Out64Reg::new(register),
+                        stack_slot: StackSlot::MEH,
+                    });
+
+                    let value: u64 = pointer.into();
+                    self.push(value as u32);
+                    self.push((value >> 32) as u32);
+
+                    self.write_to_operand(target, RegSize64, register);
+                }
+
+                // Stack variables are stored with the size of their type, so
+                // moves to and from the stack need to know the type
+                Mov { target, source } => {
+                    let (signed, size) = reg_class(op_ty);
+
+                    let op = match source {
+                        Operand::StackLocal { id } => self.load_stack(id, signed, size, 30),
+                        _ => self.operand(source, 30),
+                    };
+
+                    self.write_to_operand(target, size, op);
+                }
+
+                StackAddress { target, id } => {
+                    let register = self.output_register(target, 30);
+
+                    self.push(Opcode::MakeFp {
+                        register_out: Out64Reg::new(register),
+                        stack_id: id,
+                    });
+
+                    self.write_to_operand(target, RegSize64, register);
+                }
+
+                Load { target, pointer } => {
+                    let (signed, size) = reg_class(op_ty);
+
+                    let pointer = self.operand(pointer, 29);
+                    let register = self.output_register(target, 30);
+
+                    self.push(Opcode::Get {
+                        register_out: OutReg::new(signed, size, register),
+                        pointer: In64Reg::new(pointer),
+                    });
+
+                    self.write_to_operand(target, RegSize64, register);
+                }
+
+                Store { pointer, value } => {
+                    let (_, size) = reg_class(op_ty);
+
+                    let pointer = self.operand(pointer, 29);
+                    let value = self.operand(value, 30);
+
+                    self.push(Opcode::Set {
+                        pointer: In64Reg::new(pointer),
+                        value: InReg::new(size, value),
+                    });
+                }
+
+                HeapAlloc { target, size } => {
+                    let size = self.operand(size, 29);
+                    let register = self.output_register(target, 30);
+
+                    self.push(Opcode::HeapAlloc {
+                        register_out: Out64Reg::new(register),
+                        register_64_in: In64Reg::new(size),
+                    });
+
+                    self.write_to_operand(target, RegSize64, register);
+                }
+
+                HeapDealloc { pointer } => {
+                    let pointer = self.operand(pointer, 29);
+
+                    self.push(Opcode::HeapDealloc {
+                        register_64_in: In64Reg::new(pointer),
+                    });
+                }
+
+                PtrOffset {
+                    target,
+                    pointer,
+                    offset,
+                } => {
+                    let pointer = self.operand(pointer, 29);
+                    let offset = self.operand(offset, 30);
+                    let register = self.output_register(target, 30);
+
+                    self.push(Opcode::PtrOffset {
+                        register_out: Out64Reg::new(register),
+                        pointer: In64Reg::new(pointer),
+                        offset: In64Reg::new(offset),
+                    });
+
+                    self.write_to_operand(target, RegSize64, register);
+                }
+
+                MemCopy { dest, source, size } => {
+                    let source = self.operand(source, 29);
+                    let dest = self.operand(dest, 30);
 
-                    ConstantU64 { target, value } => {
-                        let register_out = Out64Reg::new(30);
+                    self.push(Opcode::Make64 {
+                        register_out: Out64Reg::new(31),
+                        stack_slot: StackSlot::MEH,
+                    });
 
-                        self.push(Opcode::Make64 {
+                    self.push(size);
+                    self.push(0u32);
+
+                    self.push(Opcode::MemCopy {
+                        source: In64Reg::new(source),
+                        dest: In64Reg::new(dest),
+                        byte_count: In64Reg::new(31),
+                    });
+                }
+
+                IndexCheck { index, len } => {
+                    let index = self.operand(index, 29);
+                    let len = self.operand(len, 30);
+
+                    self.push(Opcode::IndexCheck {
+                        index: In64Reg::new(index),
+                        len: In64Reg::new(len),
+                    });
+                }
+
+                RangeCheck { start, end, len } => {
+                    let start = self.operand(start, 29);
+                    let end = self.operand(end, 30);
+                    let len = self.operand(len, 31);
+
+                    self.push(Opcode::RangeCheck {
+                        start: In64Reg::new(start),
+                        end: In64Reg::new(end),
+                        len: In64Reg::new(len),
+                    });
+                }
+
+                Add {
+                    target,
+                    left,
+                    right,
+                } if op_ty.is_float() => {
+                    self.float_op(op_ty, target, left, right, |register_out, left, right| {
+                        Opcode::FAdd {
                             register_out,
-                            stack_slot: StackSlot::MEH,
-                        });
+                            left,
+                            right,
+                        }
+                    });
+                }
 
-                        self.push(value as u32);
-                        self.push((value >> 32) as u32);
+                Add {
+                    target,
+                    left,
+                    right,
+                } => {
+                    self.int_op(op_ty, target, left, right, |register_out, left, right| {
+                        Opcode::Add {
+                            register_out,
+                            left,
+                            right,
+                        }
+                    });
+                }
 
-                        self.write_to_operand(target, RegSize64, 30);
-                    }
+                Sub {
+                    target,
+                    left,
+                    right,
+                } if op_ty.is_float() => {
+                    self.float_op(op_ty, target, left, right, |register_out, left, right| {
+                        Opcode::FSub {
+                            register_out,
+                            left,
+                            right,
+                        }
+                    });
+                }
 
-                    Mov { target, source } => {
-                        let op = self.operand(source, 30);
+                Sub {
+                    target,
+                    left,
+                    right,
+                } => {
+                    self.int_op(op_ty, target, left, right, |register_out, left, right| {
+                        Opcode::Sub {
+                            register_out,
+                            left,
+                            right,
+                        }
+                    });
+                }
 
-                        self.write_to_operand(target, RegSize64, op);
-                    }
+                Mul {
+                    target,
+                    left,
+                    right,
+                } if op_ty.is_float() => {
+                    self.float_op(op_ty, target, left, right, |register_out, left, right| {
+                        Opcode::FMul {
+                            register_out,
+                            left,
+                            right,
+                        }
+                    });
+                }
 
-                    Add {
-                        target,
-                        left,
-                        right,
-                    } => {
-                        let op1 = self.operand(left, 29);
-                        let op2 = self.operand(right, 30);
-
-                        self.push(Opcode::Add {
-                            register_out: OutReg::new(RegUnsigned, RegSize64, 30),
-                            left: InReg::new(RegSize64, op1 as u8),
-                            right: InReg::new(RegSize64, op2 as u8),
-                        });
+                Mul {
+                    target,
+                    left,
+                    right,
+                } => {
+                    self.int_op(op_ty, target, left, right, |register_out, left, right| {
+                        Opcode::Mul {
+                            register_out,
+                            left,
+                            right,
+                        }
+                    });
+                }
 
-                        self.write_to_operand(target, RegSize64, 30);
-                    }
+                Div {
+                    target,
+                    left,
+                    right,
+                } if op_ty.is_float() => {
+                    self.float_op(op_ty, target, left, right, |register_out, left, right| {
+                        Opcode::FDiv {
+                            register_out,
+                            left,
+                            right,
+                        }
+                    });
+                }
+
+                Div {
+                    target,
+                    left,
+                    right,
+                } => {
+                    self.int_op(op_ty, target, left, right, |register_out, left, right| {
+                        Opcode::Div {
+                            register_out,
+                            left,
+                            right,
+                        }
+                    });
+                }
+
+                Mod {
+                    target,
+                    left,
+                    right,
+                } if op_ty.is_float() => {
+                    self.float_op(op_ty, target, left, right, |register_out, left, right| {
+                        Opcode::FMod {
+                            register_out,
+                            left,
+                            right,
+                        }
+                    });
+                }
+
+                Mod {
+                    target,
+                    left,
+                    right,
+                } => {
+                    self.int_op(op_ty, target, left, right, |register_out, left, right| {
+                        Opcode::Mod {
+                            register_out,
+                            left,
+                            right,
+                        }
+                    });
+                }
+
+                LShift {
+                    target,
+                    left,
+                    right,
+                } => {
+                    self.int_op(op_ty, target, left, right, |register_out, left, right| {
+                        Opcode::LShift {
+                            register_out,
+                            left,
+                            right,
+                        }
+                    });
+                }
+
+                RShift {
+                    target,
+                    left,
+                    right,
+                } => {
+                    self.int_op(op_ty, target, left, right, |register_out, left, right| {
+                        Opcode::RShift {
+                            register_out,
+                            left,
+                            right,
+                        }
+                    });
+                }
+
+                BitAnd {
+                    target,
+                    left,
+                    right,
+                } => {
+                    self.int_op(op_ty, target, left, right, |register_out, left, right| {
+                        Opcode::BitAnd {
+                            register_out,
+                            left,
+                            right,
+                        }
+                    });
+                }
+
+                BitOr {
+                    target,
+                    left,
+                    right,
+                } => {
+                    self.int_op(op_ty, target, left, right, |register_out, left, right| {
+                        Opcode::BitOr {
+                            register_out,
+                            left,
+                            right,
+                        }
+                    });
+                }
+
+                BitXor {
+                    target,
+                    left,
+                    right,
+                } => {
+                    self.int_op(op_ty, target, left, right, |register_out, left, right| {
+                        Opcode::BitXor {
+                            register_out,
+                            left,
+                            right,
+                        }
+                    });
+                }
+
+                CompLt {
+                    target,
+                    left,
+                    right,
+                } if op_ty.is_float() => {
+                    self.float_op(op_ty, target, left, right, |register_out, left, right| {
+                        Opcode::FCompLt {
+                            register_out,
+                            left,
+                            right,
+                        }
+                    });
+                }
+
+                CompLt {
+                    target,
+                    left,
+                    right,
+                } => {
+                    self.int_op(op_ty, target, left, right, |register_out, left, right| {
+                        Opcode::CompLt {
+                            register_out,
+                            left,
+                            right,
+                        }
+                    });
+                }
+
+                CompLeq {
+                    target,
+                    left,
+                    right,
+                } if op_ty.is_float() => {
+                    self.float_op(op_ty, target, left, right, |register_out, left, right| {
+                        Opcode::FCompLeq {
+                            register_out,
+                            left,
+                            right,
+                        }
+                    });
+                }
+
+                CompLeq {
+                    target,
+                    left,
+                    right,
+                } => {
+                    self.int_op(op_ty, target, left, right, |register_out, left, right| {
+                        Opcode::CompLeq {
+                            register_out,
+                            left,
+                            right,
+                        }
+                    });
+                }
+
+                CompEq {
+                    target,
+                    left,
+                    right,
+                } if op_ty.is_float() => {
+                    self.float_op(op_ty, target, left, right, |register_out, left, right| {
+                        Opcode::FCompEq {
+                            register_out,
+                            left,
+                            right,
+                        }
+                    });
+                }
+
+                CompEq {
+                    target,
+                    left,
+                    right,
+                } => {
+                    self.int_op(op_ty, target, left, right, |register_out, left, right| {
+                        Opcode::CompEq {
+                            register_out,
+                            left,
+                            right,
+                        }
+                    });
+                }
+
+                CompNeq {
+                    target,
+                    left,
+                    right,
+                } if op_ty.is_float() => {
+                    self.float_op(op_ty, target, left, right, |register_out, left, right| {
+                        Opcode::FCompNeq {
+                            register_out,
+                            left,
+                            right,
+                        }
+                    });
+                }
+
+                CompNeq {
+                    target,
+                    left,
+                    right,
+                } => {
+                    self.int_op(op_ty, target, left, right, |register_out, left, right| {
+                        Opcode::CompNeq {
+                            register_out,
+                            left,
+                            right,
+                        }
+                    });
+                }
+
+                BitNot { target, value } => {
+                    let (signed, size) = reg_class(op_ty);
+
+                    let op = self.operand(value, 29);
+                    let register = self.output_register(target, 30);
+
+                    self.push(Opcode::BitNot {
+                        register_out: OutReg::new(signed, size, register),
+                        left: InReg::new(size, op),
+                        right: InReg::NULL,
+                    });
+
+                    self.write_to_operand(target, RegSize64, register);
+                }
+
+                Cast {
+                    target,
+                    value,
+                    from,
+                } if from.is_float() || op_ty.is_float() => {
+                    let (in_signed, in_size) = reg_class(from);
+                    let (out_signed, out_size) = reg_class(op_ty);
 
-                    Print { value } => {
-                        let op = self.operand(value, 30);
+                    let op = self.operand(value, 29);
+                    let register = self.output_register(target, 30);
 
-                        self.push(Opcode::Ecall {
-                            kind: EcallKind::Print,
-                            input_1: In64Reg::new(op),
-                            input_2: In64Reg::NULL,
+                    let register_in = InReg::new(in_size, op);
+                    let opcode = match (from.is_float(), op_ty.is_float()) {
+                        (true, true) => Opcode::FloatToFloat {
+                            register_out: OutReg::new(RegUnsigned, out_size, register),
+                            register_in,
+                        },
+                        (true, false) => Opcode::FloatToInt {
+                            register_out: OutReg::new(out_signed, out_size, register),
+                            register_in,
+                        },
+                        _ => Opcode::IntToFloat {
+                            register_out: OutReg::new(in_signed, out_size, register),
+                            register_in,
+                        },
+                    };
+
+                    self.push(opcode);
+
+                    self.write_to_operand(target, RegSize64, register);
+                }
+
+                // Values in registers are always kept zero/sign-extended to
+                // 64 bits, so casting between integers just means truncating
+                // and re-extending in-place.
+                Cast { target, value, .. } => {
+                    let (signed, size) = reg_class(op_ty);
+
+                    let op = self.operand(value, 29);
+                    let register = self.output_register(target, 30);
+
+                    if op != register {
+                        self.push(Opcode::Mov {
+                            register_out: Out64Reg::new(register),
+                            register_in: In64Reg::new(op),
                         });
                     }
 
-                    PrintNewline => {
-                        self.push(Opcode::Ecall {
-                            kind: EcallKind::PrintNewline,
-                            input_1: In64Reg::NULL,
-                            input_2: In64Reg::NULL,
-                        });
+                    let register_out = OutReg::new(signed, size, register);
+                    match op_ty {
+                        Type::Bool => self.push(Opcode::BoolNorm {
+                            register_out,
+                            stack_slot: 0,
+                        }),
+                        _ => self.push(Opcode::Truncate {
+                            register_out,
+                            stack_slot: 0,
+                        }),
                     }
 
-                    ExitSuccess => {
-                        self.push(Opcode::Ecall {
-                            kind: EcallKind::ExitSuccess,
-                            input_1: In64Reg::NULL,
-                            input_2: In64Reg::NULL,
+                    self.write_to_operand(target, RegSize64, register);
+                }
+
+                // BoolNot happens in-place, so the value needs to get moved
+                // to the output first
+                BoolNot { target, value } => {
+                    let op = self.operand(value, 29);
+                    let register = self.output_register(target, 30);
+
+                    if op != register {
+                        self.push(Opcode::Mov {
+                            register_out: Out64Reg::new(register),
+                            register_in: In64Reg::new(op),
                         });
                     }
 
-                    _ => {
-                        unimplemented!("{:?}", op);
+                    self.push(Opcode::BoolNot {
+                        register_out: OutReg::new(RegUnsigned, RegSize64, register),
+                        stack_slot: 0,
+                    });
+
+                    self.write_to_operand(target, RegSize64, register);
+                }
+
+                // Strings get printed from the bytes pointer and the length in
+                // their header
+                Print { value } if op_ty == Type::String => {
+                    let op = self.operand(value, 31);
+
+                    if op != 31 {
+                        self.push(Opcode::Mov {
+                            register_out: Out64Reg::new(31),
+                            register_in: In64Reg::new(op),
+                        });
                     }
+
+                    self.push(Opcode::Get {
+                        register_out: OutReg::new(RegUnsigned, RegSize64, 29),
+                        pointer: In64Reg::new(31),
+                    });
+
+                    self.push(Opcode::Add16 {
+                        register_out: Out64Reg::new(31),
+                        value: 8,
+                    });
+
+                    self.push(Opcode::Get {
+                        register_out: OutReg::new(RegUnsigned, RegSize64, 30),
+                        pointer: In64Reg::new(31),
+                    });
+
+                    self.push(Opcode::Ecall {
+                        kind: EcallKind::PrintString,
+                        input_1: In64Reg::new(29),
+                        input_2: In64Reg::new(30),
+                    });
+                }
+
+                Print { value } => {
+                    let op = self.operand(value, 30);
+
+                    let kind = match op_ty {
+                        Type::Bool => EcallKind::PrintBool,
+                        Type::F32 => EcallKind::PrintF32,
+                        Type::F64 => EcallKind::PrintF64,
+                        Type::Char => EcallKind::PrintChar,
+                        ty if ty.is_signed() => EcallKind::PrintSigned,
+                        _ => EcallKind::Print,
+                    };
+
+                    self.push(Opcode::Ecall {
+                        kind,
+                        input_1: In64Reg::new(op),
+                        input_2: In64Reg::NULL,
+                    });
+                }
+
+                PrintNewline => {
+                    self.push(Opcode::Ecall {
+                        kind: EcallKind::PrintNewline,
+                        input_1: In64Reg::NULL,
+                        input_2: In64Reg::NULL,
+                    });
+                }
+
+                Call {
+                    target,
+                    block,
+                    arg_count,
+                } => {
+                    let register = self.output_register(target, 30);
+                    let register_out = match target {
+                        Operand::Null => Out64Reg::NULL,
+                        _ => Out64Reg::new(register),
+                    };
+
+                    self.push(Opcode::Call {
+                        register_out,
+                        arg_count: arg_count as u8,
+                    });
+                    self.push_block_address(block);
+
+                    self.write_to_operand(target, RegSize64, register);
+                }
+
+                Return { value } => {
+                    let value = match value {
+                        Operand::Null => In64Reg::NULL,
+                        _ => In64Reg::new(self.operand(value, 30)),
+                    };
+
+                    self.push(Opcode::Ret { value });
+                }
+
+                Jump { block } => {
+                    self.push(Opcode::Jump);
+                    self.push_block_address(block);
+                }
+
+                Branch {
+                    cond,
+                    if_true,
+                    if_false,
+                } => {
+                    let op = self.operand(cond, 30);
+
+                    self.push(Opcode::JumpIfZero {
+                        register_in: InReg::new(RegSize64, op),
+                        stack_slot: 0,
+                    });
+                    self.push_block_address(if_false);
+
+                    self.push(Opcode::Jump);
+                    self.push_block_address(if_true);
+                }
+
+                ExitSuccess => {
+                    self.push(Opcode::Ecall {
+                        kind: EcallKind::ExitSuccess,
+                        input_1: In64Reg::NULL,
+                        input_2: In64Reg::NULL,
+                    });
+                }
+
+                _ => {
+                    unimplemented!("{:?}", op);
                 }
             }
+        }
+    }
 
-        */
+    // Each string literal only gets laid out once, as a header followed by
+    // the string's bytes, all in the same static allocation.
+    fn string_header(&mut self, table: &StringTable, symbol: u32) -> Ptr {
+        if let Some(&pointer) = self.strings.get(&symbol) {
+            return pointer;
+        }
 
-        let mut binary = AllocTracker::new();
-        binary.alloc_exe(self.exe_bytes, Some(self.loc_bytes));
+        let text = table.names[symbol];
+        let len = text.len() as u32;
 
-        return binary;
+        let (header, _) = self.data.alloc_static(16 + len, self.current_expr);
+        let bytes = Ptr {
+            offset: 16,
+            ..header
+        };
+
+        let bytes_value: u64 = bytes.into();
+        expect(self.data.write(header, bytes_value));
+
+        let len_pointer = Ptr {
+            offset: 8,
+            ..header
+        };
+        expect(self.data.write(len_pointer, len as u64));
+
+        expect(self.data.write_bytes(bytes, text.as_bytes()));
+
+        self.strings.insert(symbol, header);
+
+        return header;
+    }
+
+    // Integer operations all have the same shape. The size and signedness of
+    // the registers come from the type of the operands.
+    fn int_op(
+        &mut self,
+        ty: Type,
+        target: Operand,
+        left: Operand,
+        right: Operand,
+        make: impl FnOnce(OutReg, InReg, InReg) -> Opcode,
+    ) {
+        let (signed, size) = reg_class(ty);
+
+        let op1 = self.operand(left, 29);
+        let op2 = self.operand(right, 30);
+        let register = self.output_register(target, 30);
+
+        self.push(make(
+            OutReg::new(signed, size, register),
+            InReg::new(size, op1),
+            InReg::new(size, op2),
+        ));
+
+        self.write_to_operand(target, RegSize64, register);
+    }
+
+    // Float operations write their output as raw bits, so the output register
+    // doesn't have a size or signedness
+    fn float_op(
+        &mut self,
+        ty: Type,
+        target: Operand,
+        left: Operand,
+        right: Operand,
+        make: impl FnOnce(u8, InReg, InReg) -> Opcode,
+    ) {
+        let (_, size) = reg_class(ty);
+
+        let op1 = self.operand(left, 29);
+        let op2 = self.operand(right, 30);
+        let register = self.output_register(target, 30);
+
+        self.push(make(register, InReg::new(size, op1), InReg::new(size, op2)));
+
+        self.write_to_operand(target, RegSize64, register);
+    }
+
+    // Register that an operation should write its output to. Values headed for
+    // the stack get computed in the temp register first, and then written out
+    // with write_to_operand.
+    pub fn output_register(&self, op: Operand, temp_register: u8) -> u8 {
+        match op {
+            Operand::RegisterValue { id } => return id as u8,
+            Operand::StackLocal { .. } => return temp_register,
+            Operand::Null => return temp_register,
+        }
     }
 
     pub fn write_to_operand(&mut self, op: Operand, size: RegSize, register: u8) {
@@ -137,39 +928,53 @@ impl Assembler {
             }
 
             Operand::RegisterValue { id } => {
+                if id as u8 == register {
+                    return;
+                }
+
                 self.push(Opcode::Mov {
                     register_out: Out64Reg::new(id as u8),
                     register_in: In64Reg::new(register),
                 });
             }
 
-            Operand::Null => {
-                panic!("oops");
-            }
+            // Nobody wants the value, so we don't need to write it anywhere
+            Operand::Null => {}
         }
     }
 
-    pub fn operand(&mut self, op: Operand, temp_register: u8) -> u8 {
-        match op {
-            Operand::StackLocal { id } => {
-                self.push(Opcode::MakeFp {
-                    register_out: Out64Reg::new(31),
-                    stack_id: id,
-                });
+    // Reads a stack variable into `temp_register`, zero/sign-extending it
+    pub fn load_stack(
+        &mut self,
+        id: u16,
+        signed: RegSignedness,
+        size: RegSize,
+        temp_register: u8,
+    ) -> u8 {
+        self.push(Opcode::MakeFp {
+            register_out: Out64Reg::new(31),
+            stack_id: id,
+        });
 
-                // if offset != 0 {
-                //     self.push(Opcode::Add16 {
-                //         register_out: Out64Reg::new(31),
-                //         value: offset,
-                //     });
-                // }
+        // if offset != 0 {
+        //     self.push(Opcode::Add16 {
+        //         register_out: Out64Reg::new(31),
+        //         value: offset,
+        //     });
+        // }
 
-                self.push(Opcode::Get {
-                    pointer: In64Reg::new(31),
-                    register_out: OutReg::new(RegUnsigned, RegSize64, temp_register),
-                });
+        self.push(Opcode::Get {
+            pointer: In64Reg::new(31),
+            register_out: OutReg::new(signed, size, temp_register),
+        });
 
-                return temp_register;
+        return temp_register;
+    }
+
+    pub fn operand(&mut self, op: Operand, temp_register: u8) -> u8 {
+        match op {
+            Operand::StackLocal { id } => {
+                return self.load_stack(id, RegUnsigned, RegSize64, temp_register);
             }
 
             Operand::RegisterValue { id } => {
@@ -187,8 +992,71 @@ impl Assembler {
         }
     }
 
+    pub fn push_block_address(&mut self, block: u32) {
+        let index = self.exe_bytes.len() as u32;
+        self.relocations.push(Relocation { index, block });
+
+        self.push(Self::UNPLACED);
+    }
+
     pub fn push(&mut self, val: impl Into<u32>) {
         self.exe_bytes.push(val.into());
         self.loc_bytes.push(self.current_expr);
     }
 }
+
+// Register size and signedness used for values of a type
+fn reg_class(ty: Type) -> (RegSignedness, RegSize) {
+    let signed = match ty.is_signed() {
+        true => RegSigned,
+        false => RegUnsigned,
+    };
+
+    let size = match ty.size() {
+        1 => RegSize8,
+        2 => RegSize16,
+        4 => RegSize32,
+        _ => RegSize64,
+    };
+
+    return (signed, size);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relocations() {
+        let mut graph = Graph::new();
+
+        let entry = graph.get_block_id();
+        let middle = graph.get_block_id();
+        let exit = graph.get_block_id();
+
+        let op = |kind| GraphOp::new(kind, Type::Null, ExprId::NULL);
+
+        let mut ops = Pod::new();
+        ops.push(op(GraphOpKind::Jump { block: exit }));
+        graph.write_block(entry, ops);
+
+        let mut ops = Pod::new();
+        ops.push(op(GraphOpKind::PrintNewline));
+        ops.push(op(GraphOpKind::Jump { block: entry }));
+        graph.write_block(middle, ops);
+
+        let mut ops = Pod::new();
+        ops.push(op(GraphOpKind::ExitSuccess));
+        graph.write_block(exit, ops);
+
+        // entry: Jump, address
+        // middle: Ecall, Jump, address
+        // exit: Ecall
+        let assembler = Assembler::new();
+        let exe = assembler.assemble(&StringTable::new(), &graph, entry);
+        let start = exe.manifest.static_exe_start;
+
+        assert_eq!(exe.read_op_at_index(start + 4), 20);
+        assert_eq!(exe.read_op_at_index(start + 16), 0);
+    }
+}
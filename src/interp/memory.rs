@@ -14,6 +14,10 @@ pub struct Memory {
 
     stack_pointer_map: Pod<u32>,
     stack_frames: Pod<StackFrame>,
+
+    // register blocks get reused by frames at the same call depth, instead of
+    // allocating a new one for every call
+    register_blocks: Pod<u32>,
 }
 
 impl core::ops::Deref for Memory {
@@ -56,6 +60,9 @@ impl Memory {
             registers_start: range.start,
         };
 
+        let mut register_blocks = Pod::new();
+        register_blocks.push(range.start);
+
         return Self {
             data,
 
@@ -64,6 +71,7 @@ impl Memory {
 
             stack_frames: Pod::new(),
             stack_pointer_map: Pod::new(),
+            register_blocks,
         };
     }
 
@@ -200,6 +208,12 @@ impl Memory {
         return Ok(opcode);
     }
 
+    // The expression that the current op was generated from, if the
+    // executable has location info
+    pub fn current_expr(&self) -> ExprId {
+        return self.data.expr_at(self.current_frame.program_counter);
+    }
+
     pub fn advance_pc(&mut self) {
         self.current_frame.program_counter += 4;
     }
@@ -220,15 +234,45 @@ impl Memory {
         return Ok(());
     }
 
-    pub fn call(&mut self, new_pc: u32) -> Result<(), IError> {
+    // The last `arg_count` stack variables of the caller become the first stack
+    // variables of the callee
+    pub fn call(&mut self, new_pc: u32, arg_count: u32) -> Result<(), IError> {
         if self.stack_frames.len() >= MAX_STACK_FRAMES {
             return Err(IError::new("recursion limit reached"));
         }
 
+        let stack_len = self.stack_pointer_map.len() as u32;
+        let frame_len = stack_len - self.current_frame.map_offset;
+        if arg_count > frame_len {
+            return Err(IError::new(
+                "internal error: not enough stack variables for call",
+            ));
+        }
+
+        self.check_pc(new_pc)?;
+
         self.stack_frames.push(self.current_frame);
 
-        self.current_frame.program_counter = new_pc;
-        self.current_frame.map_offset = self.stack_pointer_map.len() as u32;
+        let depth = self.stack_frames.len();
+        let registers_start = match self.register_blocks.get(depth) {
+            Some(&start) => {
+                self.data.bytes[r(start, start + 256)].fill(0);
+
+                start
+            }
+            None => {
+                let range = self.data.alloc_range(AllocLen::new(256));
+                self.register_blocks.push(range.start);
+
+                range.start
+            }
+        };
+
+        self.current_frame = StackFrame {
+            program_counter: new_pc,
+            map_offset: stack_len - arg_count,
+            registers_start,
+        };
 
         return Ok(());
     }
@@ -26,6 +26,22 @@ impl<'a> Interpreter<'a> {
     }
 
     pub fn run(&mut self) -> Result<(), IError> {
+        let result = self.run_ops();
+
+        // Errors get tagged with the expression that was running, if they
+        // don't already have a location
+        if let Err(mut e) = result {
+            if e.expr.is_null() {
+                e.expr = self.memory.current_expr();
+            }
+
+            return Err(e);
+        }
+
+        return Ok(());
+    }
+
+    fn run_ops(&mut self) -> Result<(), IError> {
         use Opcode::*;
 
         loop {
@@ -50,6 +66,39 @@ impl<'a> Interpreter<'a> {
                     self.memory.advance_pc();
                 }
 
+                HeapAlloc {
+                    register_out,
+                    register_64_in,
+                } => {
+                    let len = self.memory.read_unsigned_reg(register_64_in)?;
+                    let len = match u32::try_from(len) {
+                        Ok(len) => len,
+                        Err(_) => return Err(IError::new("allocation is too large")),
+                    };
+
+                    let pc = self.memory.current_frame.program_counter;
+                    let (ptr, _) = self.memory.alloc(len, pc);
+
+                    let id = register_out.expect_id()?;
+                    self.memory.write_register(id, ptr)?;
+
+                    self.memory.advance_pc();
+                }
+
+                HeapDealloc { register_64_in } => {
+                    let ptr: Ptr = self.memory.read_unsigned_reg(register_64_in)?.into();
+                    if ptr.offset != 0 {
+                        let message =
+                            "tried to free a pointer that isn't the start of its allocation";
+                        return Err(IError::new(message));
+                    }
+
+                    let pc = self.memory.current_frame.program_counter;
+                    self.memory.dealloc_heap(ptr, pc)?;
+
+                    self.memory.advance_pc();
+                }
+
                 Make64 {
                     register_out,
                     stack_slot,
@@ -90,7 +139,7 @@ impl<'a> Interpreter<'a> {
                     register_out,
                 } => {
                     let out = register_out.expect_id()?;
-                    let value: u64 = self.memory.read_unsigned_reg(register_in)?.into();
+                    let value = self.memory.read_unsigned_reg(register_in)?;
                     self.memory.write_register(out, value)?;
 
                     self.memory.advance_pc();
@@ -100,13 +149,13 @@ impl<'a> Interpreter<'a> {
                     let size_class = value.size_class();
                     let pointer: Ptr = self.memory.read_unsigned_reg(pointer)?.into();
 
-                    let value: u64 = self.memory.read_unsigned_reg(value)?.into();
+                    let value = self.memory.read_unsigned_reg(value)?;
 
                     match size_class {
                         0 => self.memory.write(pointer, value as u8)?,
                         1 => self.memory.write(pointer, value as u16)?,
                         2 => self.memory.write(pointer, value as u32)?,
-                        3 => self.memory.write(pointer, value as u64)?,
+                        3 => self.memory.write(pointer, value)?,
                         _ => {
                             panic!("invalid size class: {}", size_class);
                         }
@@ -127,7 +176,7 @@ impl<'a> Interpreter<'a> {
                         0 => self.memory.read::<u8>(pointer)? as u64,
                         1 => self.memory.read::<u16>(pointer)? as u64,
                         2 => self.memory.read::<u32>(pointer)? as u64,
-                        3 => self.memory.read::<u64>(pointer)? as u64,
+                        3 => self.memory.read::<u64>(pointer)?,
                         _ => {
                             panic!("invalid size class: {}", out_size);
                         }
@@ -146,209 +195,1351 @@ impl<'a> Interpreter<'a> {
                     self.memory.advance_pc();
                 }
 
-                Add {
+                Make16 {
                     register_out,
-                    left,
-                    right,
+                    value,
                 } => {
-                    let sign_extend = register_out.is_signed();
-                    let out_size = register_out.size_class();
+                    let value = match register_out.is_signed() {
+                        true => value as i16 as u64,
+                        false => value as u64,
+                    };
+
+                    self.write_output(register_out, value)?;
+
+                    self.memory.advance_pc();
+                }
+
+                Make32 {
+                    register_out,
+                    stack_slot,
+                } => {
+                    self.memory.advance_pc();
 
-                    let result = if sign_extend {
-                        let left = self.memory.read_signed_reg(left)?;
-                        let right = self.memory.read_signed_reg(right)?;
+                    let value = self.memory.read_op()?;
 
-                        let result = (left.wrapping_add(right)) as u64;
+                    if register_out.id().is_some() {
+                        let value = match register_out.is_signed() {
+                            true => value as i32 as u64,
+                            false => value as u64,
+                        };
 
-                        sign_extend_and_truncate(out_size, result) as u64
+                        self.write_output(register_out, value)?;
                     } else {
-                        let left = self.memory.read_unsigned_reg(left)?;
-                        let right = self.memory.read_unsigned_reg(right)?;
+                        let slot = StackSlot {
+                            id: stack_slot as u8,
+                            offset: (stack_slot >> 8) as u8,
+                        };
+
+                        let ptr = self.memory.stack_slot_ptr(slot)?;
+                        self.memory.write(ptr, value)?;
+                    }
+
+                    self.memory.advance_pc();
+                }
+
+                PtrOffset {
+                    register_out,
+                    pointer,
+                    offset,
+                } => {
+                    let mut ptr: Ptr = self.memory.read_unsigned_reg(pointer)?.into();
+                    let offset = self.memory.read_signed_reg(offset)?;
+
+                    // Out of bounds pointers are fine, as long as nobody reads
+                    // or writes with them
+                    ptr.offset = (ptr.offset as i64).wrapping_add(offset) as u32;
+
+                    let id = register_out.expect_id()?;
+                    self.memory.write_register(id, ptr)?;
+
+                    self.memory.advance_pc();
+                }
 
-                        let result = left.wrapping_add(right);
+                MemCopy {
+                    source,
+                    dest,
+                    byte_count,
+                } => {
+                    let source: Ptr = self.memory.read_unsigned_reg(source)?.into();
+                    let dest: Ptr = self.memory.read_unsigned_reg(dest)?.into();
+                    let len = self.memory.read_unsigned_reg(byte_count)?;
 
-                        truncate(out_size, result)
+                    let len = match u32::try_from(len) {
+                        Ok(len) => len,
+                        Err(_) => return Err(IError::new("copy is too large")),
                     };
 
-                    let out = register_out.expect_id()?;
-                    self.memory.write_register(out, result)?;
+                    self.memory.memcpy(dest, source, len)?;
 
                     self.memory.advance_pc();
                 }
 
-                Ecall {
-                    kind,
-                    input_1,
-                    input_2,
-                } => match kind {
-                    EcallKind::ExitSuccess => break,
-                    EcallKind::Print => {
-                        let left = self.memory.read_unsigned_reg(input_1)?;
+                IndexCheck { index, len } => {
+                    let index = self.memory.read_signed_reg(index)?;
+                    let len = self.memory.read_signed_reg(len)?;
 
-                        let err = |_| IError::new("failed to write");
-                        write!(self.out, "{} ", left).map_err(err)?;
+                    if index < 0 || index >= len {
+                        let message = format!(
+                            "index out of range: the index is {} but the length is {}",
+                            index, len
+                        );
 
-                        self.memory.advance_pc();
+                        return Err(IError::new(&message));
                     }
-                    EcallKind::PrintNewline => {
-                        let err = |_| IError::new("failed to write");
-                        self.out.write_str("\n").map_err(err)?;
 
-                        self.memory.advance_pc();
+                    self.memory.advance_pc();
+                }
+
+                RangeCheck { start, end, len } => {
+                    let start = self.memory.read_signed_reg(start)?;
+                    let end = self.memory.read_signed_reg(end)?;
+                    let len = self.memory.read_signed_reg(len)?;
+
+                    if end > len {
+                        let message = format!(
+                            "slice out of range: the end is {} but the length is {}",
+                            end, len
+                        );
+
+                        return Err(IError::new(&message));
                     }
 
-                    #[allow(unreachable_patterns)]
-                    _ => {
-                        panic!("invalid kind {}", kind as u8);
+                    if start < 0 || start > end {
+                        let message = format!(
+                            "slice out of range: the start is {} but the end is {}",
+                            start, end
+                        );
+
+                        return Err(IError::new(&message));
                     }
-                },
 
-                _ => {
-                    unimplemented!("{:?}", opcode);
+                    self.memory.advance_pc();
                 }
-            }
-        }
 
-        return Ok(());
-    }
-}
+                Add16 {
+                    register_out,
+                    value,
+                } => {
+                    let id = register_out.expect_id()?;
+                    let previous = self.memory.read_register(id)?;
+                    self.memory
+                        .write_register(id, previous.wrapping_add(value as u64))?;
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use Opcode::*;
+                    self.memory.advance_pc();
+                }
 
-    #[test]
-    fn simple() {
-        let mut data = AllocTracker::new();
+                Truncate { register_out, .. } => {
+                    let id = register_out.expect_id()?;
+                    let value = self.memory.read_register(id)?;
+                    self.write_output(register_out, value)?;
 
-        let mut ops: Pod<u32> = Pod::new();
+                    self.memory.advance_pc();
+                }
 
-        let value_2_16: u64 = 65536;
-        let value_2_15: u64 = 32768;
+                BoolNorm { register_out, .. } => {
+                    let id = register_out.expect_id()?;
+                    let value = self.memory.read_register(id)?;
+                    self.memory.write_register(id, (value != 0) as u64)?;
 
-        ops.push(
-            StackAlloc {
-                len: AllocLen::new(8),
-                save_address: Out64Reg::new(1),
-            }
-            .into(),
-        );
+                    self.memory.advance_pc();
+                }
 
-        ops.push(
-            Make64 {
-                register_out: Out64Reg::new(2),
-                stack_slot: StackSlot { id: 0, offset: 0 },
-            }
-            .into(),
-        );
+                BoolNot { register_out, .. } => {
+                    let id = register_out.expect_id()?;
+                    let value = self.memory.read_register(id)?;
+                    self.memory.write_register(id, (value == 0) as u64)?;
 
-        ops.push(value_2_16 as u32);
-        ops.push(0);
+                    self.memory.advance_pc();
+                }
 
-        ops.push(
-            Make64 {
-                register_out: Out64Reg::new(3),
-                stack_slot: StackSlot { id: 0, offset: 0 },
-            }
-            .into(),
-        );
+                Add {
+                    register_out,
+                    left,
+                    right,
+                } => {
+                    let unsigned = |l: u64, r: u64| Ok(l.wrapping_add(r));
+                    let signed = |l: i64, r: i64| Ok(l.wrapping_add(r));
 
-        ops.push(value_2_15 as u32);
-        ops.push(0);
+                    self.int_op(register_out, left, right, unsigned, signed)?;
 
-        ops.push(
-            Add {
-                register_out: OutReg::new(RegUnsigned, RegSize64, 4),
-                left: InReg::new(RegSize16, 2),
-                right: InReg::new(RegSize16, 3),
-            }
-            .into(),
-        );
+                    self.memory.advance_pc();
+                }
 
-        ops.push(
-            Add {
-                register_out: OutReg::new(RegUnsigned, RegSize16, 5),
-                left: InReg::new(RegSize16, 2),
-                right: InReg::new(RegSize16, 3),
-            }
-            .into(),
-        );
+                Sub {
+                    register_out,
+                    left,
+                    right,
+                } => {
+                    let unsigned = |l: u64, r: u64| Ok(l.wrapping_sub(r));
+                    let signed = |l: i64, r: i64| Ok(l.wrapping_sub(r));
 
-        ops.push(
-            Add {
-                register_out: OutReg::new(RegUnsigned, RegSize64, 6),
-                left: InReg::new(RegSize16, 2),
-                right: InReg::new(RegSize16, 3),
-            }
-            .into(),
-        );
+                    self.int_op(register_out, left, right, unsigned, signed)?;
 
-        ops.push(
-            Add {
-                register_out: OutReg::new(RegUnsigned, RegSize64, 7),
-                left: InReg::new(RegSize64, 2),
-                right: InReg::new(RegSize64, 3),
-            }
-            .into(),
-        );
+                    self.memory.advance_pc();
+                }
 
-        ops.push(
-            Add {
-                register_out: OutReg::new(RegSigned, RegSize16, 8),
-                left: InReg::new(RegSize64, 2),
-                right: InReg::new(RegSize64, 3),
-            }
-            .into(),
-        );
+                Mul {
+                    register_out,
+                    left,
+                    right,
+                } => {
+                    let unsigned = |l: u64, r: u64| Ok(l.wrapping_mul(r));
+                    let signed = |l: i64, r: i64| Ok(l.wrapping_mul(r));
 
-        ops.push(StackDealloc { count: 1 }.into());
+                    self.int_op(register_out, left, right, unsigned, signed)?;
 
-        ops.push(
-            Ecall {
-                kind: EcallKind::ExitSuccess,
-                input_1: In64Reg::NULL,
-                input_2: In64Reg::NULL,
-            }
-            .into(),
-        );
+                    self.memory.advance_pc();
+                }
 
-        data.alloc_exe(ops, None);
+                Div {
+                    register_out,
+                    left,
+                    right,
+                } => {
+                    let unsigned = |l: u64, r: u64| l.checked_div(r).ok_or_else(divide_by_zero);
+                    let signed = |l: i64, r: i64| match r {
+                        0 => Err(divide_by_zero()),
+                        _ => Ok(l.wrapping_div(r)),
+                    };
 
-        let mut out = String::new();
+                    self.int_op(register_out, left, right, unsigned, signed)?;
 
-        let mut interp = Interpreter::new(data, &mut out);
+                    self.memory.advance_pc();
+                }
 
-        let result = interp.run();
+                Mod {
+                    register_out,
+                    left,
+                    right,
+                } => {
+                    let unsigned = |l: u64, r: u64| l.checked_rem(r).ok_or_else(divide_by_zero);
+                    let signed = |l: i64, r: i64| match r {
+                        0 => Err(divide_by_zero()),
+                        _ => Ok(l.wrapping_rem(r)),
+                    };
 
-        let sum = value_2_16 + value_2_15;
-        let sign_extended = value_2_15 as i16 as i64 as u64;
-        assert_eq!(interp.memory.read_register(2).unwrap(), value_2_16);
-        assert_eq!(interp.memory.read_register(3).unwrap(), value_2_15);
-        assert_eq!(interp.memory.read_register(4).unwrap(), value_2_15);
-        assert_eq!(interp.memory.read_register(5).unwrap(), value_2_15);
-        assert_eq!(interp.memory.read_register(6).unwrap(), value_2_15);
-        assert_eq!(interp.memory.read_register(7).unwrap(), sum);
-        assert_eq!(interp.memory.read_register(8).unwrap(), sign_extended);
+                    self.int_op(register_out, left, right, unsigned, signed)?;
 
-        match result {
-            Ok(_) => {}
-            Err(e) => {
-                println!("{:?}", e);
+                    self.memory.advance_pc();
+                }
 
-                println!(
-                    "pc after error: {}",
-                    interp.memory.current_frame.program_counter
-                );
+                RShift {
+                    register_out,
+                    left,
+                    right,
+                } => {
+                    let unsigned = |l: u64, r: u64| Ok(l.wrapping_shr(r as u32));
+                    let signed = |l: i64, r: i64| Ok(l.wrapping_shr(r as u32));
 
-                let manifest = interp.memory.manifest;
-                println!(
-                    "static_exe: {}..{}",
-                    manifest.static_exe_start, manifest.static_exe_end
-                );
+                    self.int_op(register_out, left, right, unsigned, signed)?;
 
-                panic!("Failed");
-            }
-        }
+                    self.memory.advance_pc();
+                }
+
+                LShift {
+                    register_out,
+                    left,
+                    right,
+                } => {
+                    let unsigned = |l: u64, r: u64| Ok(l.wrapping_shl(r as u32));
+                    let signed = |l: i64, r: i64| Ok(l.wrapping_shl(r as u32));
+
+                    self.int_op(register_out, left, right, unsigned, signed)?;
+
+                    self.memory.advance_pc();
+                }
+
+                BitAnd {
+                    register_out,
+                    left,
+                    right,
+                } => {
+                    let left = self.memory.read_unsigned_reg(left)?;
+                    let right = self.memory.read_unsigned_reg(right)?;
+                    self.write_output(register_out, left & right)?;
+
+                    self.memory.advance_pc();
+                }
+
+                BitOr {
+                    register_out,
+                    left,
+                    right,
+                } => {
+                    let left = self.memory.read_unsigned_reg(left)?;
+                    let right = self.memory.read_unsigned_reg(right)?;
+                    self.write_output(register_out, left | right)?;
+
+                    self.memory.advance_pc();
+                }
+
+                BitXor {
+                    register_out,
+                    left,
+                    right,
+                } => {
+                    let left = self.memory.read_unsigned_reg(left)?;
+                    let right = self.memory.read_unsigned_reg(right)?;
+                    self.write_output(register_out, left ^ right)?;
+
+                    self.memory.advance_pc();
+                }
+
+                // Unary, so the right register is ignored
+                BitNot {
+                    register_out, left, ..
+                } => {
+                    let left = self.memory.read_unsigned_reg(left)?;
+                    self.write_output(register_out, !left)?;
+
+                    self.memory.advance_pc();
+                }
+
+                CompLt {
+                    register_out,
+                    left,
+                    right,
+                } => {
+                    let unsigned = |l: u64, r: u64| Ok((l < r) as u64);
+                    let signed = |l: i64, r: i64| Ok((l < r) as i64);
+
+                    self.int_op(register_out, left, right, unsigned, signed)?;
+
+                    self.memory.advance_pc();
+                }
+
+                CompLeq {
+                    register_out,
+                    left,
+                    right,
+                } => {
+                    let unsigned = |l: u64, r: u64| Ok((l <= r) as u64);
+                    let signed = |l: i64, r: i64| Ok((l <= r) as i64);
+
+                    self.int_op(register_out, left, right, unsigned, signed)?;
+
+                    self.memory.advance_pc();
+                }
+
+                CompEq {
+                    register_out,
+                    left,
+                    right,
+                } => {
+                    let unsigned = |l: u64, r: u64| Ok((l == r) as u64);
+                    let signed = |l: i64, r: i64| Ok((l == r) as i64);
+
+                    self.int_op(register_out, left, right, unsigned, signed)?;
+
+                    self.memory.advance_pc();
+                }
+
+                CompNeq {
+                    register_out,
+                    left,
+                    right,
+                } => {
+                    let unsigned = |l: u64, r: u64| Ok((l != r) as u64);
+                    let signed = |l: i64, r: i64| Ok((l != r) as i64);
+
+                    self.int_op(register_out, left, right, unsigned, signed)?;
+
+                    self.memory.advance_pc();
+                }
+
+                FAdd {
+                    register_out,
+                    left,
+                    right,
+                } => {
+                    let op32 = |l: f32, r: f32| (l + r).to_bits() as u64;
+                    let op64 = |l: f64, r: f64| (l + r).to_bits();
+
+                    self.float_op(register_out, left, right, op32, op64)?;
+
+                    self.memory.advance_pc();
+                }
+
+                FSub {
+                    register_out,
+                    left,
+                    right,
+                } => {
+                    let op32 = |l: f32, r: f32| (l - r).to_bits() as u64;
+                    let op64 = |l: f64, r: f64| (l - r).to_bits();
+
+                    self.float_op(register_out, left, right, op32, op64)?;
+
+                    self.memory.advance_pc();
+                }
+
+                FMul {
+                    register_out,
+                    left,
+                    right,
+                } => {
+                    let op32 = |l: f32, r: f32| (l * r).to_bits() as u64;
+                    let op64 = |l: f64, r: f64| (l * r).to_bits();
+
+                    self.float_op(register_out, left, right, op32, op64)?;
+
+                    self.memory.advance_pc();
+                }
+
+                FDiv {
+                    register_out,
+                    left,
+                    right,
+                } => {
+                    let op32 = |l: f32, r: f32| (l / r).to_bits() as u64;
+                    let op64 = |l: f64, r: f64| (l / r).to_bits();
+
+                    self.float_op(register_out, left, right, op32, op64)?;
+
+                    self.memory.advance_pc();
+                }
+
+                FMod {
+                    register_out,
+                    left,
+                    right,
+                } => {
+                    let op32 = |l: f32, r: f32| (l % r).to_bits() as u64;
+                    let op64 = |l: f64, r: f64| (l % r).to_bits();
+
+                    self.float_op(register_out, left, right, op32, op64)?;
+
+                    self.memory.advance_pc();
+                }
+
+                FCompLt {
+                    register_out,
+                    left,
+                    right,
+                } => {
+                    let op32 = |l: f32, r: f32| (l < r) as u64;
+                    let op64 = |l: f64, r: f64| (l < r) as u64;
+
+                    self.float_op(register_out, left, right, op32, op64)?;
+
+                    self.memory.advance_pc();
+                }
+
+                FCompLeq {
+                    register_out,
+                    left,
+                    right,
+                } => {
+                    let op32 = |l: f32, r: f32| (l <= r) as u64;
+                    let op64 = |l: f64, r: f64| (l <= r) as u64;
+
+                    self.float_op(register_out, left, right, op32, op64)?;
+
+                    self.memory.advance_pc();
+                }
+
+                FCompEq {
+                    register_out,
+                    left,
+                    right,
+                } => {
+                    let op32 = |l: f32, r: f32| (l == r) as u64;
+                    let op64 = |l: f64, r: f64| (l == r) as u64;
+
+                    self.float_op(register_out, left, right, op32, op64)?;
+
+                    self.memory.advance_pc();
+                }
+
+                FCompNeq {
+                    register_out,
+                    left,
+                    right,
+                } => {
+                    let op32 = |l: f32, r: f32| (l != r) as u64;
+                    let op64 = |l: f64, r: f64| (l != r) as u64;
+
+                    self.float_op(register_out, left, right, op32, op64)?;
+
+                    self.memory.advance_pc();
+                }
+
+                IntToFloat {
+                    register_out,
+                    register_in,
+                } => {
+                    let value = match register_out.is_signed() {
+                        true => self.memory.read_signed_reg(register_in)? as f64,
+                        false => self.memory.read_unsigned_reg(register_in)? as f64,
+                    };
+
+                    let bits = match register_out.size_class() == RegSize32 as u8 {
+                        true => (value as f32).to_bits() as u64,
+                        false => value.to_bits(),
+                    };
+
+                    let out = register_out.expect_id()?;
+                    self.memory.write_register(out, bits)?;
+
+                    self.memory.advance_pc();
+                }
+
+                FloatToInt {
+                    register_out,
+                    register_in,
+                } => {
+                    let value = self.read_float(register_in)?;
+
+                    // Rust's float to int casts saturate, so values past
+                    // the 64 bit range don't wrap before getting truncated.
+                    // Smaller sizes are clamped here first.
+                    let bits = 8u32 << register_out.size_class();
+                    let value = match register_out.is_signed() {
+                        true => {
+                            let max = (i64::MAX >> (64 - bits)) as f64;
+                            let min = (i64::MIN >> (64 - bits)) as f64;
+                            value.clamp(min, max) as i64 as u64
+                        }
+                        false => {
+                            let max = (u64::MAX >> (64 - bits)) as f64;
+                            value.clamp(0.0, max) as u64
+                        }
+                    };
+
+                    self.write_output(register_out, value)?;
+
+                    self.memory.advance_pc();
+                }
+
+                FloatToFloat {
+                    register_out,
+                    register_in,
+                } => {
+                    let value = self.read_float(register_in)?;
+
+                    let bits = match register_out.size_class() == RegSize32 as u8 {
+                        true => (value as f32).to_bits() as u64,
+                        false => value.to_bits(),
+                    };
+
+                    let out = register_out.expect_id()?;
+                    self.memory.write_register(out, bits)?;
+
+                    self.memory.advance_pc();
+                }
+
+                Jump => {
+                    self.memory.advance_pc();
+
+                    let address = self.memory.read_op()?;
+                    self.jump_to(address)?;
+                }
+
+                JumpIfZero { register_in, .. } => {
+                    self.memory.advance_pc();
+
+                    let value = self.memory.read_unsigned_reg(register_in)?;
+                    let address = self.memory.read_op()?;
+
+                    if value == 0 {
+                        self.jump_to(address)?;
+                    } else {
+                        self.memory.advance_pc();
+                    }
+                }
+
+                JumpIfNotZero { register_in, .. } => {
+                    self.memory.advance_pc();
+
+                    let value = self.memory.read_unsigned_reg(register_in)?;
+                    let address = self.memory.read_op()?;
+
+                    if value != 0 {
+                        self.jump_to(address)?;
+                    } else {
+                        self.memory.advance_pc();
+                    }
+                }
+
+                Call {
+                    register_out,
+                    arg_count,
+                } => {
+                    self.memory.advance_pc();
+                    let address = self.memory.read_op()?;
+
+                    // The caller's frame is saved pointing at the call, so that
+                    // Ret can find register-output again
+                    self.memory.current_frame.program_counter -= 4;
+
+                    let return_pointer = match register_out.id() {
+                        Some(id) => self.memory.read_register(id)?,
+                        None => 0,
+                    };
+
+                    let start = self.memory.manifest.static_exe_start;
+                    let new_pc = start.saturating_add(address);
+                    self.memory.call(new_pc, arg_count as u32)?;
+
+                    self.memory
+                        .write_register(REGISTER_CALL_ID, return_pointer)?;
+                }
+
+                Ret { value } => {
+                    let result = match value.id() {
+                        Some(_) => Some(self.memory.read_unsigned_reg(value)?),
+                        None => None,
+                    };
+
+                    self.memory.ret()?;
+
+                    let register_out = match self.memory.read_op()?.into() {
+                        Call { register_out, .. } => register_out,
+                        _ => {
+                            return Err(IError::new("internal error: returned to non-call"));
+                        }
+                    };
+
+                    if let (Some(id), Some(result)) = (register_out.id(), result) {
+                        self.memory.write_register(id, result)?;
+                    }
+
+                    // skip over the call and its address
+                    self.memory.advance_pc();
+                    self.memory.advance_pc();
+                }
+
+                Ecall {
+                    kind,
+                    input_1,
+                    input_2,
+                } => match kind {
+                    EcallKind::ExitSuccess => break,
+                    EcallKind::Print => {
+                        let left = self.memory.read_unsigned_reg(input_1)?;
+
+                        let err = |_| IError::new("failed to write");
+                        write!(self.out, "{} ", left).map_err(err)?;
+
+                        self.memory.advance_pc();
+                    }
+                    EcallKind::PrintSigned => {
+                        let value = self.memory.read_signed_reg(input_1)?;
+
+                        let err = |_| IError::new("failed to write");
+                        write!(self.out, "{} ", value).map_err(err)?;
+
+                        self.memory.advance_pc();
+                    }
+                    EcallKind::PrintF32 => {
+                        let value = self.memory.read_unsigned_reg(input_1)?;
+                        let value = f32::from_bits(value as u32);
+
+                        let err = |_| IError::new("failed to write");
+                        write!(self.out, "{} ", value).map_err(err)?;
+
+                        self.memory.advance_pc();
+                    }
+                    EcallKind::PrintF64 => {
+                        let value = self.memory.read_unsigned_reg(input_1)?;
+                        let value = f64::from_bits(value);
+
+                        let err = |_| IError::new("failed to write");
+                        write!(self.out, "{} ", value).map_err(err)?;
+
+                        self.memory.advance_pc();
+                    }
+                    EcallKind::PrintString => {
+                        let pointer: Ptr = self.memory.read_unsigned_reg(input_1)?.into();
+                        let len = self.memory.read_unsigned_reg(input_2)?;
+
+                        let len = match u32::try_from(len) {
+                            Ok(len) => len,
+                            Err(_) => return Err(IError::new("string is too long")),
+                        };
+
+                        let bytes = self.memory.read_bytes(pointer, len)?;
+                        let text = match core::str::from_utf8(bytes) {
+                            Ok(text) => text,
+                            Err(_) => return Err(IError::new("string isn't valid UTF-8")),
+                        };
+
+                        let err = |_| IError::new("failed to write");
+                        write!(self.out, "{} ", text).map_err(err)?;
+
+                        self.memory.advance_pc();
+                    }
+                    EcallKind::PrintChar => {
+                        let value = self.memory.read_unsigned_reg(input_1)?;
+
+                        let c = match u32::try_from(value).ok().and_then(char::from_u32) {
+                            Some(c) => c,
+                            None => return Err(IError::new("invalid char")),
+                        };
+
+                        let err = |_| IError::new("failed to write");
+                        write!(self.out, "{} ", c).map_err(err)?;
+
+                        self.memory.advance_pc();
+                    }
+                    EcallKind::PrintBool => {
+                        let value = self.memory.read_unsigned_reg(input_1)?;
+                        let value = value != 0;
+
+                        let err = |_| IError::new("failed to write");
+                        write!(self.out, "{} ", value).map_err(err)?;
+
+                        self.memory.advance_pc();
+                    }
+                    EcallKind::PrintNewline => {
+                        let err = |_| IError::new("failed to write");
+                        self.out.write_str("\n").map_err(err)?;
+
+                        self.memory.advance_pc();
+                    }
+
+                    #[allow(unreachable_patterns)]
+                    _ => {
+                        panic!("invalid kind {}", kind as u8);
+                    }
+                },
+
+                _ => {
+                    unimplemented!("{:?}", opcode);
+                }
+            }
+        }
+
+        return Ok(());
+    }
+
+    // Jump addresses are byte offsets from the start of the executable, so that
+    // the assembler doesn't need to know where the executable will end up
+    fn jump_to(&mut self, address: u32) -> Result<(), IError> {
+        let start = self.memory.manifest.static_exe_start;
+
+        return self.memory.jmp(start.saturating_add(address));
+    }
+
+    // Writes the value to the output register, truncating or sign-extending
+    // it based on the register's flags
+    fn write_output(&mut self, register_out: OutReg, value: u64) -> Result<(), IError> {
+        let out_size = register_out.size_class();
+
+        let value = if register_out.is_signed() {
+            sign_extend_and_truncate(out_size, value) as u64
+        } else {
+            truncate(out_size, value)
+        };
+
+        let out = register_out.expect_id()?;
+        self.memory.write_register(out, value)?;
+
+        return Ok(());
+    }
+
+    // 32 bit registers hold f32 values, and everything else holds f64 values
+    fn read_float(&self, register_in: InReg) -> Result<f64, IError> {
+        let bits = self.memory.read_unsigned_reg(register_in)?;
+
+        if register_in.size_class() == RegSize32 as u8 {
+            return Ok(f32::from_bits(bits as u32) as f64);
+        }
+
+        return Ok(f64::from_bits(bits));
+    }
+
+    // The float width is decided by the size of the left input
+    fn float_op(
+        &mut self,
+        register_out: u8,
+        left: InReg,
+        right: InReg,
+        op32: impl FnOnce(f32, f32) -> u64,
+        op64: impl FnOnce(f64, f64) -> u64,
+    ) -> Result<(), IError> {
+        let l = self.memory.read_unsigned_reg(left)?;
+        let r = self.memory.read_unsigned_reg(right)?;
+
+        let result = if left.size_class() == RegSize32 as u8 {
+            op32(f32::from_bits(l as u32), f32::from_bits(r as u32))
+        } else {
+            op64(f64::from_bits(l), f64::from_bits(r))
+        };
+
+        self.memory.write_register(register_out, result)?;
+
+        return Ok(());
+    }
+
+    // register-output signed-ness determines both the sign-extension of inputs
+    // into 64 bits and also which version of the operation gets used
+    fn int_op(
+        &mut self,
+        register_out: OutReg,
+        left: InReg,
+        right: InReg,
+        unsigned: impl FnOnce(u64, u64) -> Result<u64, IError>,
+        signed: impl FnOnce(i64, i64) -> Result<i64, IError>,
+    ) -> Result<(), IError> {
+        let result = if register_out.is_signed() {
+            let left = self.memory.read_signed_reg(left)?;
+            let right = self.memory.read_signed_reg(right)?;
+
+            signed(left, right)? as u64
+        } else {
+            let left = self.memory.read_unsigned_reg(left)?;
+            let right = self.memory.read_unsigned_reg(right)?;
+
+            unsigned(left, right)?
+        };
+
+        return self.write_output(register_out, result);
+    }
+}
+
+fn divide_by_zero() -> IError {
+    return IError::new("divided by zero");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Opcode::*;
+
+    #[test]
+    fn simple() {
+        let mut data = AllocTracker::new();
+
+        let mut ops: Pod<u32> = Pod::new();
+
+        let value_2_16: u64 = 65536;
+        let value_2_15: u64 = 32768;
+
+        ops.push(
+            StackAlloc {
+                len: AllocLen::new(8),
+                save_address: Out64Reg::new(1),
+            }
+            .into(),
+        );
+
+        ops.push(
+            Make64 {
+                register_out: Out64Reg::new(2),
+                stack_slot: StackSlot { id: 0, offset: 0 },
+            }
+            .into(),
+        );
+
+        ops.push(value_2_16 as u32);
+        ops.push(0);
+
+        ops.push(
+            Make64 {
+                register_out: Out64Reg::new(3),
+                stack_slot: StackSlot { id: 0, offset: 0 },
+            }
+            .into(),
+        );
+
+        ops.push(value_2_15 as u32);
+        ops.push(0);
+
+        ops.push(
+            Add {
+                register_out: OutReg::new(RegUnsigned, RegSize64, 4),
+                left: InReg::new(RegSize16, 2),
+                right: InReg::new(RegSize16, 3),
+            }
+            .into(),
+        );
+
+        ops.push(
+            Add {
+                register_out: OutReg::new(RegUnsigned, RegSize16, 5),
+                left: InReg::new(RegSize16, 2),
+                right: InReg::new(RegSize16, 3),
+            }
+            .into(),
+        );
+
+        ops.push(
+            Add {
+                register_out: OutReg::new(RegUnsigned, RegSize64, 6),
+                left: InReg::new(RegSize16, 2),
+                right: InReg::new(RegSize16, 3),
+            }
+            .into(),
+        );
+
+        ops.push(
+            Add {
+                register_out: OutReg::new(RegUnsigned, RegSize64, 7),
+                left: InReg::new(RegSize64, 2),
+                right: InReg::new(RegSize64, 3),
+            }
+            .into(),
+        );
+
+        ops.push(
+            Add {
+                register_out: OutReg::new(RegSigned, RegSize16, 8),
+                left: InReg::new(RegSize64, 2),
+                right: InReg::new(RegSize64, 3),
+            }
+            .into(),
+        );
+
+        ops.push(StackDealloc { count: 1 }.into());
+
+        ops.push(
+            Ecall {
+                kind: EcallKind::ExitSuccess,
+                input_1: In64Reg::NULL,
+                input_2: In64Reg::NULL,
+            }
+            .into(),
+        );
+
+        data.alloc_exe(ops, None);
+
+        let mut out = String::new();
+
+        let mut interp = Interpreter::new(data, &mut out);
+
+        let result = interp.run();
+
+        let sum = value_2_16 + value_2_15;
+        let sign_extended = value_2_15 as i16 as i64 as u64;
+        assert_eq!(interp.memory.read_register(2).unwrap(), value_2_16);
+        assert_eq!(interp.memory.read_register(3).unwrap(), value_2_15);
+        assert_eq!(interp.memory.read_register(4).unwrap(), value_2_15);
+        assert_eq!(interp.memory.read_register(5).unwrap(), value_2_15);
+        assert_eq!(interp.memory.read_register(6).unwrap(), value_2_15);
+        assert_eq!(interp.memory.read_register(7).unwrap(), sum);
+        assert_eq!(interp.memory.read_register(8).unwrap(), sign_extended);
+
+        match result {
+            Ok(_) => {}
+            Err(e) => {
+                println!("{:?}", e);
+
+                println!(
+                    "pc after error: {}",
+                    interp.memory.current_frame.program_counter
+                );
+
+                let manifest = interp.memory.manifest;
+                println!(
+                    "static_exe: {}..{}",
+                    manifest.static_exe_start, manifest.static_exe_end
+                );
+
+                panic!("Failed");
+            }
+        }
+    }
+
+    #[test]
+    fn alu() {
+        let mut data = AllocTracker::new();
+
+        let mut ops: Pod<u32> = Pod::new();
+
+        let u64_out = |id| OutReg::new(RegUnsigned, RegSize64, id);
+        let s64_out = |id| OutReg::new(RegSigned, RegSize64, id);
+        let in64 = |id| InReg::new(RegSize64, id);
+
+        ops.push(
+            Make16 {
+                register_out: u64_out(1),
+                value: 7,
+            }
+            .into(),
+        );
+        ops.push(
+            Make16 {
+                register_out: s64_out(2),
+                value: -20i16 as u16,
+            }
+            .into(),
+        );
+        ops.push(
+            Make32 {
+                register_out: u64_out(3),
+                stack_slot: 0,
+            }
+            .into(),
+        );
+        ops.push(300);
+
+        ops.push(
+            Sub {
+                register_out: s64_out(4),
+                left: in64(1),
+                right: in64(2),
+            }
+            .into(),
+        );
+        ops.push(
+            Div {
+                register_out: s64_out(5),
+                left: in64(2),
+                right: in64(1),
+            }
+            .into(),
+        );
+        ops.push(
+            Mod {
+                register_out: s64_out(6),
+                left: in64(2),
+                right: in64(1),
+            }
+            .into(),
+        );
+        ops.push(
+            RShift {
+                register_out: s64_out(7),
+                left: in64(2),
+                right: in64(1),
+            }
+            .into(),
+        );
+        ops.push(
+            LShift {
+                register_out: OutReg::new(RegUnsigned, RegSize8, 8),
+                left: in64(3),
+                right: in64(1),
+            }
+            .into(),
+        );
+        ops.push(
+            BitNot {
+                register_out: OutReg::new(RegUnsigned, RegSize16, 9),
+                left: in64(1),
+                right: InReg::NULL,
+            }
+            .into(),
+        );
+        ops.push(
+            Mul {
+                register_out: OutReg::new(RegSigned, RegSize8, 10),
+                left: in64(3),
+                right: in64(1),
+            }
+            .into(),
+        );
+        ops.push(
+            BoolNot {
+                register_out: u64_out(1),
+                stack_slot: 0,
+            }
+            .into(),
+        );
+        ops.push(
+            Div {
+                register_out: u64_out(11),
+                left: in64(3),
+                right: in64(1),
+            }
+            .into(),
+        );
+
+        data.alloc_exe(ops, None);
+
+        let mut out = String::new();
+
+        let mut interp = Interpreter::new(data, &mut out);
+
+        let result = interp.run();
+        assert!(result.is_err(), "division by zero should be an error");
+
+        let read = |id| interp.memory.read_register(id).unwrap();
+        assert_eq!(read(2), -20i64 as u64);
+        assert_eq!(read(3), 300);
+        assert_eq!(read(4), 27);
+        assert_eq!(read(5), -2i64 as u64);
+        assert_eq!(read(6), -6i64 as u64);
+        assert_eq!(read(7), -1i64 as u64);
+        assert_eq!(read(8), (300u64 << 7) as u8 as u64);
+        assert_eq!(read(9), !7u16 as u64);
+        assert_eq!(read(10), 2100u64 as i8 as i64 as u64);
+        assert_eq!(read(1), 0);
+    }
+
+    #[test]
+    fn control_flow() {
+        let mut data = AllocTracker::new();
+
+        let mut ops: Pod<u32> = Pod::new();
+
+        let u64_out = |id| OutReg::new(RegUnsigned, RegSize64, id);
+        let in64 = |id| InReg::new(RegSize64, id);
+
+        // counter = 0; limit = 10; one = 1
+        ops.push(
+            Make16 {
+                register_out: u64_out(1),
+                value: 0,
+            }
+            .into(),
+        );
+        ops.push(
+            Make16 {
+                register_out: u64_out(2),
+                value: 10,
+            }
+            .into(),
+        );
+        ops.push(
+            Make16 {
+                register_out: u64_out(3),
+                value: 1,
+            }
+            .into(),
+        );
+
+        // loop: counter += 1; if counter < limit goto loop
+        let loop_address = ops.len() as u32 * 4;
+        ops.push(
+            Add {
+                register_out: u64_out(1),
+                left: in64(1),
+                right: in64(3),
+            }
+            .into(),
+        );
+        ops.push(
+            CompLt {
+                register_out: u64_out(4),
+                left: in64(1),
+                right: in64(2),
+            }
+            .into(),
+        );
+        ops.push(
+            JumpIfNotZero {
+                register_in: in64(4),
+                stack_slot: 0,
+            }
+            .into(),
+        );
+        ops.push(loop_address);
+
+        // skip over the bad write
+        let exit_address = ops.len() as u32 * 4 + 20;
+        ops.push(
+            JumpIfZero {
+                register_in: in64(4),
+                stack_slot: 0,
+            }
+            .into(),
+        );
+        ops.push(exit_address);
+
+        ops.push(
+            Make16 {
+                register_out: u64_out(1),
+                value: 1234,
+            }
+            .into(),
+        );
+        ops.push(Jump.into());
+        ops.push(u32::MAX);
+
+        ops.push(
+            Ecall {
+                kind: EcallKind::ExitSuccess,
+                input_1: In64Reg::NULL,
+                input_2: In64Reg::NULL,
+            }
+            .into(),
+        );
+
+        data.alloc_exe(ops, None);
+
+        let mut out = String::new();
+
+        let mut interp = Interpreter::new(data, &mut out);
+
+        interp.run().unwrap();
+
+        assert_eq!(interp.memory.read_register(1).unwrap(), 10);
+        assert_eq!(interp.memory.read_register(4).unwrap(), 0);
+    }
+
+    #[test]
+    fn procedures() {
+        let mut data = AllocTracker::new();
+
+        let mut ops: Pod<u32> = Pod::new();
+
+        let u64_out = |id| OutReg::new(RegUnsigned, RegSize64, id);
+        let in64 = |id| InReg::new(RegSize64, id);
+
+        // sum(5)
+        ops.push(
+            StackAlloc {
+                len: AllocLen::new(8),
+                save_address: Out64Reg::new(1),
+            }
+            .into(),
+        );
+        ops.push(
+            Make16 {
+                register_out: u64_out(2),
+                value: 5,
+            }
+            .into(),
+        );
+        ops.push(
+            Set {
+                pointer: In64Reg::new(1),
+                value: in64(2),
+            }
+            .into(),
+        );
+        ops.push(
+            Call {
+                register_out: Out64Reg::new(3),
+                arg_count: 1,
+            }
+            .into(),
+        );
+        ops.push(u32::MAX);
+        let sum_call = ops.len() - 1;
+
+        ops.push(
+            Ecall {
+                kind: EcallKind::ExitSuccess,
+                input_1: In64Reg::NULL,
+                input_2: In64Reg::NULL,
+            }
+            .into(),
+        );
+
+        // proc sum(n) { if n == 0 { return 0 }; return n + sum(n - 1) }
+        let sum_address = ops.len() as u32 * 4;
+        ops[sum_call] = sum_address;
+
+        ops.push(
+            MakeFp {
+                register_out: Out64Reg::new(1),
+                stack_id: 0,
+            }
+            .into(),
+        );
+        ops.push(
+            Get {
+                register_out: u64_out(2),
+                pointer: In64Reg::new(1),
+            }
+            .into(),
+        );
+        ops.push(
+            JumpIfZero {
+                register_in: in64(2),
+                stack_slot: 0,
+            }
+            .into(),
+        );
+        ops.push(u32::MAX);
+        let base_case_jump = ops.len() - 1;
+
+        ops.push(
+            Make16 {
+                register_out: u64_out(3),
+                value: 1,
+            }
+            .into(),
+        );
+        ops.push(
+            Sub {
+                register_out: u64_out(4),
+                left: in64(2),
+                right: in64(3),
+            }
+            .into(),
+        );
+        ops.push(
+            StackAlloc {
+                len: AllocLen::new(8),
+                save_address: Out64Reg::new(5),
+            }
+            .into(),
+        );
+        ops.push(
+            Set {
+                pointer: In64Reg::new(5),
+                value: in64(4),
+            }
+            .into(),
+        );
+        ops.push(
+            Call {
+                register_out: Out64Reg::new(6),
+                arg_count: 1,
+            }
+            .into(),
+        );
+        ops.push(sum_address);
+        ops.push(
+            Add {
+                register_out: u64_out(7),
+                left: in64(6),
+                right: in64(2),
+            }
+            .into(),
+        );
+        ops.push(
+            Ret {
+                value: In64Reg::new(7),
+            }
+            .into(),
+        );
+
+        ops[base_case_jump] = ops.len() as u32 * 4;
+        ops.push(
+            Ret {
+                value: In64Reg::new(2),
+            }
+            .into(),
+        );
+
+        data.alloc_exe(ops, None);
+
+        let mut out = String::new();
+
+        let mut interp = Interpreter::new(data, &mut out);
+
+        interp.run().unwrap();
+
+        assert_eq!(interp.memory.read_register(2).unwrap(), 5);
+        assert_eq!(interp.memory.read_register(3).unwrap(), 15);
+    }
+
+    #[test]
+    fn recursion_limit() {
+        let mut data = AllocTracker::new();
+
+        let mut ops: Pod<u32> = Pod::new();
+
+        ops.push(
+            Call {
+                register_out: Out64Reg::NULL,
+                arg_count: 0,
+            }
+            .into(),
+        );
+        ops.push(0);
+
+        data.alloc_exe(ops, None);
+
+        let mut out = String::new();
+
+        let mut interp = Interpreter::new(data, &mut out);
+
+        assert!(interp.run().is_err());
     }
 }
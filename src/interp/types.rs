@@ -3,13 +3,30 @@ use core::{fmt, mem};
 
 #[derive(Debug)]
 pub struct IError {
-    message: String,
+    pub message: String,
+
+    // The expression that was running when the error happened
+    pub expr: ExprId,
+
+    // Other places in the code that are relevant to the error, like where
+    // some memory was allocated
+    pub related: Vec<(&'static str, ExprId)>,
 }
 
 impl IError {
     pub fn new(message: &str) -> Self {
         return Self {
             message: message.to_string(),
+            expr: ExprId::NULL,
+            related: Vec::new(),
+        };
+    }
+
+    pub fn with_related(message: &str, related: Vec<(&'static str, ExprId)>) -> Self {
+        return Self {
+            message: message.to_string(),
+            expr: ExprId::NULL,
+            related,
         };
     }
 }
@@ -161,7 +178,7 @@ impl fmt::Debug for Out64Reg {
             "unsigned"
         };
 
-        return write!(f, "{}", format!("Out64Reg({}, {:?})", signed, self.id()));
+        return write!(f, "Out64Reg({}, {:?})", signed, self.id());
     }
 }
 
@@ -296,6 +313,10 @@ impl AllocLen {
 
         return len;
     }
+
+    pub fn is_empty(self) -> bool {
+        return self.len == 0;
+    }
 }
 
 // Invariants:
@@ -431,6 +452,15 @@ pub enum Opcode {
         register_out: Out64Reg,
         register_in: In64Reg,
     },
+
+    // Adds a signed byte offset to the offset part of the pointer, so that
+    // the result always stays in the same allocation
+    // opcode u8-register-output u8-register-pointer-input u8-register-64-input
+    PtrOffset {
+        register_out: Out64Reg,
+        pointer: In64Reg,
+        offset: In64Reg,
+    },
     // opcode u8-register-output u8-register-pointer-input u8
     Get {
         register_out: OutReg,
@@ -450,6 +480,20 @@ pub enum Opcode {
         byte_count: In64Reg,
     },
 
+    // Errors if the s64 in index is negative, or isn't less than len
+    // opcode u8-register-64-input u8-register-64-input u8
+    IndexCheck {
+        index: In64Reg,
+        len: In64Reg,
+    },
+    // Errors unless 0 <= start <= end <= len, where all of them are s64
+    // opcode u8-register-64-input u8-register-64-input u8-register-64-input
+    RangeCheck {
+        start: In64Reg,
+        end: In64Reg,
+        len: In64Reg,
+    },
+
     // Wrapping Integer operations
     // register-output signed-ness determines both the sign-extension of inputs
     // into 64 bits and also the operation signed-ness
@@ -524,6 +568,9 @@ pub enum Opcode {
     },
 
     // Floating point
+    // Register-input size determines the float width; 32 bit inputs are f32,
+    // and 64 bit inputs are f64. Floats are stored in registers as their raw
+    // bits, zero-extended.
     // opcode u8-register-output u8-register-input u8-register-input
     FAdd {
         register_out: u8,
@@ -554,6 +601,52 @@ pub enum Opcode {
         left: InReg,
         right: InReg,
     },
+    // opcode u8-register-output u8-register-input u8-register-input
+    FCompLt {
+        register_out: u8,
+        left: InReg,
+        right: InReg,
+    },
+    // opcode u8-register-output u8-register-input u8-register-input
+    FCompLeq {
+        register_out: u8,
+        left: InReg,
+        right: InReg,
+    },
+    // opcode u8-register-output u8-register-input u8-register-input
+    FCompEq {
+        register_out: u8,
+        left: InReg,
+        right: InReg,
+    },
+    // opcode u8-register-output u8-register-input u8-register-input
+    FCompNeq {
+        register_out: u8,
+        left: InReg,
+        right: InReg,
+    },
+
+    // Conversions
+    // register-output size determines the float width, and its signed-ness
+    // is the signed-ness of the input integer
+    // opcode u8-register-output u8-register-input u8
+    IntToFloat {
+        register_out: OutReg,
+        register_in: InReg,
+    },
+    // register-input size determines the float width; register-output is the
+    // integer type to convert to. Out of range values saturate.
+    // opcode u8-register-output u8-register-input u8
+    FloatToInt {
+        register_out: OutReg,
+        register_in: InReg,
+    },
+    // register sizes determine the float widths
+    // opcode u8-register-output u8-register-input u8
+    FloatToFloat {
+        register_out: OutReg,
+        register_in: InReg,
+    },
 
     // register-output size is implicitly ignored, because its not relevant here
     // register-output signed-ness determines both the sign-extension of inputs
@@ -589,16 +682,21 @@ pub enum Opcode {
     Jump,
     // opcode u8-register-input u16-stack-slot u32-address
     JumpIfZero {
-        register_in: u8,
+        register_in: InReg,
         stack_slot: u16,
     },
     // opcode u8-register-input u16-stack-slot u32-address
     JumpIfNotZero {
-        register_in: u8,
+        register_in: InReg,
         stack_slot: u16,
     },
 
-    Ret, // opcode u8 u16
+    // If the register input is null, the caller's register-output is unmodified;
+    // otherwise, its value is written to the caller's register-output
+    // opcode u8-register-64-input u16
+    Ret {
+        value: In64Reg,
+    },
 
     // args are allocated through stack allocs, then the call instruction sets
     // the frame pointer to the correct value using arg-count
@@ -608,6 +706,10 @@ pub enum Opcode {
     // in, and register-output is unmodified. Otherwise, output is written to
     // register-output
     //
+    // The value of register-output at the time of the call is passed to the
+    // callee in register 0, which is how the caller tells the callee where to
+    // put large return values. Each frame gets its own block of registers.
+    //
     // The jump here stays in the same allocation as it started in; the address
     // parameter only touches the offset part of the pointer
     // opcode u8-register-output u8-arg-count u8 u32-address
@@ -639,17 +741,25 @@ pub enum EcallKind {
     ExitSuccess = 0,
     Print = 1,
     PrintNewline = 2,
+    PrintBool = 3,
+    PrintSigned = 4,
+    PrintF32 = 5,
+    PrintF64 = 6,
+    // input_1 is a pointer to the string's bytes, and input_2 is its length
+    PrintString = 7,
+    // input_1 is a unicode code point
+    PrintChar = 8,
 }
 
 impl From<u32> for Opcode {
     fn from(value: u32) -> Opcode {
-        return unsafe { core::mem::transmute(value) };
+        return unsafe { core::mem::transmute::<u32, Opcode>(value) };
     }
 }
 
-impl Into<u32> for Opcode {
-    fn into(self) -> u32 {
-        return unsafe { core::mem::transmute(self) };
+impl From<Opcode> for u32 {
+    fn from(value: Opcode) -> u32 {
+        return unsafe { core::mem::transmute::<Opcode, u32>(value) };
     }
 }
 
@@ -664,13 +774,13 @@ pub struct Ptr {
 
 impl From<u64> for Ptr {
     fn from(value: u64) -> Ptr {
-        return unsafe { core::mem::transmute(value) };
+        return unsafe { core::mem::transmute::<u64, Ptr>(value) };
     }
 }
 
-impl Into<u64> for Ptr {
-    fn into(self) -> u64 {
-        return unsafe { core::mem::transmute(self) };
+impl From<Ptr> for u64 {
+    fn from(value: Ptr) -> u64 {
+        return unsafe { core::mem::transmute::<Ptr, u64>(value) };
     }
 }
 
@@ -717,6 +827,8 @@ pub enum AllocInfo {
 }
 
 impl AllocInfo {
+    // Errors from here don't know where things happened, so AllocTracker
+    // fills that in
     pub fn get_range(self) -> Result<(u32, u32), IError> {
         use AllocInfo::*;
 
@@ -728,14 +840,16 @@ impl AllocInfo {
             | Static { start, len, .. } => (start, len.len()),
 
             StackDead { creation_op } => {
-                return Err(IError::new("stackdead"));
+                return Err(IError::new(
+                    "used a pointer to a stack variable after its scope ended",
+                ));
             }
 
             HeapDead {
                 creation_op,
                 dealloc_op,
             } => {
-                return Err(IError::new("stackdead"));
+                return Err(IError::new("used memory after it was freed"));
             }
         };
 
@@ -794,7 +908,7 @@ impl AllocTracker {
         let start = self.bytes.len() as u32;
 
         let lossy_len = len.len();
-        if len.len() == 0 {
+        if len.is_empty() {
             return r(start, start);
         }
 
@@ -838,7 +952,7 @@ impl AllocTracker {
         self.manifest.static_exe_end = range.start + op_count * 4;
 
         let ops = unsafe { from_raw_parts_mut(pointer, op_count as usize) };
-        ops.copy_from_slice(&*exe);
+        ops.copy_from_slice(&exe);
 
         if let Some(locs) = locs {
             assert_eq!(op_count, locs.len() as u32);
@@ -858,7 +972,7 @@ impl AllocTracker {
             self.manifest.static_exe_loc_start = range.start;
 
             let locs_out = unsafe { from_raw_parts_mut(pointer, op_count as usize) };
-            locs_out.copy_from_slice(&*locs);
+            locs_out.copy_from_slice(&locs);
         }
     }
 
@@ -869,8 +983,9 @@ impl AllocTracker {
         let range = self.alloc_range(alloc_len);
         let start = range.start;
 
-        let info = StaticExe {
-            start: range.start,
+        let info = Static {
+            creation_expr,
+            start,
             len: alloc_len,
         };
 
@@ -976,8 +1091,14 @@ impl AllocTracker {
                 creation_op,
                 dealloc_op,
             } => {
-                return Err(IError::new(
+                let related = vec![
+                    ("allocated here", self.expr_at(creation_op)),
+                    ("freed here", self.expr_at(dealloc_op)),
+                ];
+
+                return Err(IError::with_related(
                     "tried to free memory that has already been freed (aka double-free)",
+                    related,
                 ));
             }
 
@@ -1063,20 +1184,56 @@ impl AllocTracker {
         };
     }
 
+    // The expression that the op at program counter `pc` was generated from,
+    // if the executable has location info
+    pub fn expr_at(&self, pc: u32) -> ExprId {
+        let manifest = self.manifest;
+        if manifest.static_exe_loc_start == u32::MAX {
+            return ExprId::NULL;
+        }
+
+        if pc < manifest.static_exe_start || pc >= manifest.static_exe_end {
+            return ExprId::NULL;
+        }
+
+        let index = manifest.static_exe_loc_start + (pc - manifest.static_exe_start);
+        let pointer = &self.bytes[index] as *const u8 as *const ExprId;
+
+        return unsafe { *pointer };
+    }
+
     pub fn get_range(&self, ptr: Ptr, len: u32) -> Result<CopyRange<u32>, IError> {
         let alloc_info = self.get_alloc_info(ptr)?;
 
-        let (start, alloc_len) = alloc_info.get_range()?;
+        let (start, alloc_len) = match alloc_info.get_range() {
+            Ok(range) => range,
+            Err(mut e) => {
+                if let AllocInfo::HeapDead {
+                    creation_op,
+                    dealloc_op,
+                } = alloc_info
+                {
+                    e.related
+                        .push(("allocated here", self.expr_at(creation_op)));
+                    e.related.push(("freed here", self.expr_at(dealloc_op)));
+                }
+
+                return Err(e);
+            }
+        };
 
-        let ptr_offset_end = ptr.offset + len;
-        if ptr_offset_end > alloc_len {
-            return Err(IError::new("invalid pointer"));
-        }
+        let ptr_offset_end = match ptr.offset.checked_add(len) {
+            Some(end) if end <= alloc_len => end,
+            _ => return Err(IError::new("pointer is out of bounds")),
+        };
 
         return Ok(r(start + ptr.offset, start + ptr_offset_end));
     }
 }
 
+/// # Safety
+///
+/// Every byte pattern has to be a valid `T`, since the bytes can be written to
 pub unsafe fn any_as_u8_slice_mut<T: Sized + Copy>(p: &mut T) -> &mut [u8] {
     core::slice::from_raw_parts_mut(p as *mut T as *mut u8, mem::size_of::<T>())
 }
@@ -1131,13 +1288,10 @@ mod tests {
             ((255 << 20) + (1 << 11), 256 << 20),
         ];
 
-        let mut i = 0;
-        for &(input_len, expected_len) in tests {
+        for (i, &(input_len, expected_len)) in tests.iter().enumerate() {
             let compressed = AllocLen::new(input_len);
 
             assert_eq!(expected_len, compressed.len(), "index: {}", i);
-
-            i += 1;
         }
     }
 
@@ -4,6 +4,9 @@
 #![allow(unused_macros)]
 #![allow(unused_braces)]
 #![allow(non_upper_case_globals)]
+#![allow(clippy::needless_return)]
+#![allow(clippy::new_without_default)]
+#![allow(clippy::manual_range_contains)]
 // Short-term allows
 /* */
 #![allow(unused_imports)]
@@ -43,14 +46,408 @@ mod tests {
     use codespan_reporting::term::termcolor;
     use std::io::Write;
 
-    // #[test]
-    // fn procedures() {
-    //     run_on_file("procedures.liu", "");
-    // }
+    #[test]
+    fn numbers() {
+        run_on_file(
+            "numbers.liu",
+            "255 65535 10 15 1000000 \n10 255 -1 -128 9223372036854775807 \n255 \n3855 \n18446744073709551615 -9223372036854775808 \n",
+        );
+    }
+
+    #[test]
+    fn bad_numbers() {
+        assert_eq!(error_text("print(0b102)\n"), "2");
+        assert_eq!(error_text("print(0o8)\n"), "8");
+        assert_eq!(error_text("print(0xfg)\n"), "g");
+        assert_eq!(error_text("print(12a)\n"), "a");
+        assert_eq!(error_text("print(0x)\n"), "0x");
+        assert_eq!(error_text("print(10u7)\n"), "u7");
+        assert_eq!(error_text("print(256u8)\n"), "256u8");
+        assert_eq!(error_text("print(-129s8)\n"), "-129s8");
+        assert_eq!(
+            error_text("print(18446744073709551616)\n"),
+            "18446744073709551616"
+        );
+        assert_eq!(error_text("let a: u8 = 1\nprint(a + 300)\n"), "300");
+    }
+
+    #[test]
+    fn operators() {
+        run_on_file(
+            "operators.liu",
+            "7 3 1 14 \n16 64 8 14 6 \ntrue false true false true false \ntrue false true \n0 \nfalse \n2 \ntrue \n0 \n4 \ntrue \n1 \ntrue \n99 \n210 \n-7549136 \n",
+        );
+    }
+
+    #[test]
+    fn procedures() {
+        run_on_file("procedures.liu", "3 \n2 \n");
+    }
+
+    #[test]
+    fn procedure_params() {
+        run_on_file("procedure_params.liu", "69 \n73 1 \n5 6 \n");
+    }
+
+    #[test]
+    fn assign() {
+        run_on_file("assign.liu", "2 \n5 \n10 \n1 \n8 \n9 \n7 \n10 \n");
+    }
+
+    #[test]
+    fn assign_undeclared() {
+        let text = "let a = 1\nb = a\n";
+        assert_eq!(error_text(text), "b");
+    }
+
+    #[test]
+    fn defer() {
+        run_on_file(
+            "defer.liu",
+            "3 \n2 \n1 \n0 \n0 10 \n1 10 \n2 10 \n21 \n20 \n31 \n30 \n1 \n31 \n32 \n30 \n0 \n40 \n99 \n",
+        );
+    }
+
+    #[test]
+    fn if_else() {
+        run_on_file("if_else.liu", "2 \n3 \n20 30 \n4 5 \n0 17 29 99 \n");
+    }
+
+    #[test]
+    fn integers() {
+        run_on_file(
+            "integers.liu",
+            "4 \n127 \n-42 -6 true -75 \n255 -1 -1 65236 \ntrue true 0 false \n-5 3 \n-2 \n-1 \n0 \n0 201 \n",
+        );
+    }
+
+    #[test]
+    fn floats() {
+        run_on_file(
+            "floats.liu",
+            "1.5 0.25 2 -3.5 1000.5 \n2.5 3 -2.5 3.5 1.5 \n0.5 4 -0.25 \ntrue true true false true false \n3.5 \n-2 255 0 127 \n7 -3 0.25 0.1 \n1 1 \n1.5 \n",
+        );
+    }
+
+    #[test]
+    fn bad_floats() {
+        assert_eq!(error_text("print(1.5f16)\n"), "f16");
+        assert_eq!(error_text("print(1.5a)\n"), "a");
+        assert_eq!(error_text("print(1.5 << 1)\n"), "1.5 << 1");
+        assert_eq!(error_text("print(bool(1.5))\n"), "bool(1.5)");
+        assert_eq!(error_text("let a: f32 = 1.5f64\n"), "1.5f64");
+    }
+
+    #[test]
+    fn strings() {
+        run_on_file(
+            "strings.liu",
+            "hello world \ncount: 3 \ntab\there quote \"q\" AB H\u{e9}\u{1F600} \nhey \ndone \nline\nbreak \n \n",
+        );
+    }
+
+    #[test]
+    fn bad_strings() {
+        assert_eq!(error_text("print(\"a\\qb\")\n"), "\\q");
+        assert_eq!(error_text("print(\"\\x4\")\n"), "\\x4");
+        assert_eq!(error_text("print(\"\\x80\")\n"), "\\x80");
+        assert_eq!(error_text("print(\"\\u{110000}\")\n"), "\\u{110000}");
+        assert_eq!(error_text("print(\"\\u{}\")\n"), "\\u{");
+        assert_eq!(error_text("let a = \"a\" + 1\n"), "\"a\" + 1");
+    }
+
+    #[test]
+    fn chars() {
+        run_on_file(
+            "chars.liu",
+            "a Z true ' \" \u{e9} \u{1F600} \ntrue true false 120 B \na \nb \nc \n",
+        );
+    }
+
+    #[test]
+    fn bad_chars() {
+        assert_eq!(error_text("print('ab')\n"), "'ab'");
+        assert_eq!(error_text("print('')\n"), "''");
+        assert_eq!(error_text("print('a' + 'b')\n"), "'a' + 'b'");
+        assert_eq!(error_text("print(char(1.5))\n"), "char(1.5)");
+    }
+
+    #[test]
+    fn comments() {
+        run_on_file("comments.liu", "3 \n3 9 \n5 \n");
+    }
+
+    #[test]
+    fn unterminated_comment() {
+        assert_eq!(error_text("print(1)\n/* a /* b */\n"), "/*");
+        assert_eq!(error_text("print(1 /* + 2\n"), "/*");
+    }
+
+    #[test]
+    fn comment_locations() {
+        // Errors after a comment should still point at the right text
+        let text = "/* \u{e9} /* */ */ let a = /*\n*/ b\n";
+        assert_eq!(error_text(text), "b");
+    }
+
+    #[test]
+    fn pointers() {
+        run_on_file(
+            "pointers.liu",
+            "20 20 \n25 \n3 4 \n42 \n7 true false \ntrue \n",
+        );
+    }
+
+    #[test]
+    fn bad_pointers() {
+        assert_eq!(error_text("let a = 1\nprint(*a)\n"), "a");
+        assert_eq!(error_text("print(&1)\n"), "1");
+        assert_eq!(error_text("let a = 1\nlet p: *s64 = &a\n"), "&a");
+        assert_eq!(error_text("let a = 1\nlet p = &a\n*p = true\n"), "true");
+    }
+
+    #[test]
+    fn pointer_errors() {
+        let text = "proc dangle() -> *u64 {\n    let a = 1\n    return &a\n}\nprint(*dangle())\n";
+        let e = runtime_error(text);
+        let message = "used a pointer to a stack variable after its scope ended";
+        assert_eq!(e.message, message);
+        assert_eq!(expr_text(text, e.expr), "*dangle()");
+
+        let text = "let a = 1\nlet p = &a + 1\nprint(*p)\n";
+        let e = runtime_error(text);
+        assert_eq!(e.message, "pointer is out of bounds");
+        assert_eq!(expr_text(text, e.expr), "*p");
+    }
+
+    #[test]
+    fn heap() {
+        run_on_file("heap.liu", "0 -10 -20 -30 \n255 1 0 \n6 \n9 \n");
+    }
+
+    #[test]
+    fn heap_errors() {
+        let text = "let p = alloc(u8, 2)\nlet q = p\nfree(p)\nfree(q)\n";
+        let e = runtime_error(text);
+        assert!(e.message.contains("double-free"));
+        assert_eq!(expr_text(text, e.expr), "free(q)");
+        assert_eq!(expr_text(text, e.related[0].1), "alloc(u8, 2)");
+        assert_eq!(expr_text(text, e.related[1].1), "free(p)");
+
+        let text = "let p = alloc(s64, 1)\nfree(p)\nprint(*p)\n";
+        let e = runtime_error(text);
+        assert_eq!(e.message, "used memory after it was freed");
+        assert_eq!(expr_text(text, e.expr), "*p");
+        assert_eq!(expr_text(text, e.related[0].1), "alloc(s64, 1)");
+        assert_eq!(expr_text(text, e.related[1].1), "free(p)");
+
+        let text = "let p = alloc(u8, 2)\n*(p + 2) = 1\n";
+        let e = runtime_error(text);
+        assert_eq!(e.message, "pointer is out of bounds");
+        assert_eq!(expr_text(text, e.expr), "*(p + 2) = 1");
+
+        let text = "let a = 1\nfree(&a)\n";
+        let message = "tried to free memory that isn't on the heap";
+        assert_eq!(runtime_error(text).message, message);
+    }
+
+    #[test]
+    fn arrays() {
+        run_on_file(
+            "arrays.liu",
+            "1 4 \n20 13 \n255 1 7 \n2 30 -4 \n20 13 \n99 \n13 \n6 11 3 2 \nfalse 30 \n1 \n2 \n",
+        );
+    }
+
+    #[test]
+    fn bad_arrays() {
+        assert_eq!(error_text("let a = [1, true]\n"), "true");
+        assert_eq!(error_text("let a: [2]u8 = [1, 2, 3]\n"), "[1, 2, 3]");
+        assert_eq!(error_text("let a = 1\nprint(a[0])\n"), "a");
+        assert_eq!(error_text("let a = [1]\nprint(a[true])\n"), "true");
+        assert_eq!(error_text("let n = 2\nlet a: [n]u8 = [1, 2]\n"), "n");
+        assert_eq!(error_text("print([1, 2])\n"), "[1, 2]");
+        assert_eq!(error_text("let a = [1]\na[0] = 1.5\n"), "1.5");
+        assert_eq!(error_text("let a = [1]\na.len = 2\n"), "a.len");
+        assert_eq!(error_text("let a = [1]\nprint(a.size)\n"), "a");
+    }
+
+    #[test]
+    fn structs() {
+        run_on_file(
+            "structs.liu",
+            "1 2 \n10 7 \n10 3 \ntrue -1 4 7 \n11 8 10 \n16 \nfalse 4 -1 7 \n1 40 \n42 \n2 \n10 \n1 12 1 \n6 7 \n",
+        );
+    }
+
+    #[test]
+    fn bad_structs() {
+        let point = "type Point struct { x: s64, y: s64 }\n";
+        let text = format!("{}let p = Point{{x: 1}}\n", point);
+        assert_eq!(error_text(&text), "Point{x: 1}");
+        let text = format!("{}let p = Point{{x: 1, z: 2}}\n", point);
+        assert_eq!(error_text(&text), "z: 2");
+        let text = format!("{}let p = Point{{x: 1, y: true}}\n", point);
+        assert_eq!(error_text(&text), "true");
+        let text = format!("{}let p = Point{{x: 1, y: 2}}\nprint(p.z)\n", point);
+        assert_eq!(error_text(&text), "p.z");
+        let text = format!("{}let p = Point{{x: 1, y: 2}}\nprint(p)\n", point);
+        assert_eq!(error_text(&text), "p");
+
+        let text = "type A struct { b: B }\ntype B struct { a: A }\n";
+        assert_eq!(error_text(text), "a: A");
+        assert_eq!(error_text("let a = 1\nprint(a.x)\n"), "a");
+        assert_eq!(error_text("type A struct { a: s64, a: u8 }\n"), "a: u8");
+    }
+
+    #[test]
+    fn enums() {
+        run_on_file(
+            "enums.liu",
+            "1 2 3 \ntrue false \n20 \n40 true \n30 false \ntrue \n7 8 \n0 250 \n",
+        );
+    }
+
+    #[test]
+    fn bad_enums() {
+        let color = "type Color enum { Red, Green, Blue }\n";
+        let text = format!(
+            "{}let c = Color.Red\nmatch c {{\n    Color.Green => 1\n}}\n",
+            color
+        );
+        let message = match run_on_file_err(&text) {
+            Err(Error::Simple { message, .. }) => message,
+            _ => panic!("expected an error"),
+        };
+        assert_eq!(message, "match is missing variants: Red, Blue");
+        assert_eq!(error_text(&text), "c");
+
+        let text = format!(
+            "{}match Color.Red {{\n    _ => 1\n    Color.Red => 2\n}}\n",
+            color
+        );
+        assert_eq!(error_text(&text), "Color.Red => 2");
+        let text = format!(
+            "{}match Color.Red {{\n    Color.Red => 1\n    Color.Red => 2\n    _ => 3\n}}\n",
+            color
+        );
+        assert_eq!(error_text(&text), "Color.Red");
+        let text = format!("{}print(Color.Purple)\n", color);
+        assert_eq!(error_text(&text), "Color.Purple");
+        let text = format!("{}match 1 {{\n    _ => 1\n}}\n", color);
+        assert_eq!(error_text(&text), "1");
+        assert_eq!(error_text("type A enum { B, B }\n"), "B");
+    }
+
+    #[test]
+    fn unions() {
+        run_on_file(
+            "unions.liu",
+            "12 12 0 \n1.5 \n7.5 0 \n7 \n3 \ntrue \n7 \n1 \n-1 \n-2 \n3 \n5 0 9 \n",
+        );
+    }
+
+    #[test]
+    fn bad_unions() {
+        let shape = "type Shape enum { Circle(f64), Rect(f64, f64) }\n";
+        let wrap = "type Wrap enum { One(Shape), Two(Shape, Shape) }\n";
+        let text = format!(
+            "{}{}let w = Wrap.One(Shape.Circle(1.0))\nmatch w {{\n    Wrap.One(Shape.Rect(a, b)) => 1\n    Wrap.Two(_, s) => 2\n}}\n",
+            shape, wrap
+        );
+        let message = match run_on_file_err(&text) {
+            Err(Error::Simple { message, .. }) => message,
+            _ => panic!("expected an error"),
+        };
+        assert_eq!(message, "match is missing variants: One(Circle(_))");
+        assert_eq!(error_text(&text), "w");
+
+        let text = format!(
+            "{}let s = Shape.Rect(1.0, 2.0)\nmatch s {{\n    Shape.Rect(w, _) => 1\n    Shape.Circle(r) => 2\n    Shape.Rect(_, h) => 3\n}}\n",
+            shape
+        );
+        assert_eq!(error_text(&text), "Shape.Rect(_, h)");
+        let text = format!(
+            "{}let s = Shape.Circle(1.0)\nmatch s {{\n    Shape.Circle(r, _) => 1\n    _ => 2\n}}\n",
+            shape
+        );
+        assert_eq!(error_text(&text), "Shape.Circle(r, _)");
+        let text = format!("{}let s = Shape.Rect(1.0)\n", shape);
+        assert_eq!(error_text(&text), "Shape.Rect(1.0)");
+        let text = format!("{}let s = Shape.Circle(true)\n", shape);
+        assert_eq!(error_text(&text), "true");
+        let text = format!("{}print(Shape.Circle(1.0))\n", shape);
+        assert_eq!(error_text(&text), "Shape.Circle(1.0)");
+        assert_eq!(error_text("type A enum { B(A) }\n"), "A");
+    }
+
+    #[test]
+    fn array_errors() {
+        let text = "let a = [1, 2, 3]\nlet i = 3\nprint(a[i])\n";
+        let e = runtime_error(text);
+        let message = "index out of range: the index is 3 but the length is 3";
+        assert_eq!(e.message, message);
+        assert_eq!(expr_text(text, e.expr), "i");
+
+        let text = "let a = [1, 2]\nlet s = a[0..2]\ns[-1] = 5\n";
+        let e = runtime_error(text);
+        let message = "index out of range: the index is -1 but the length is 2";
+        assert_eq!(e.message, message);
+        assert_eq!(expr_text(text, e.expr), "-1");
+
+        let text = "let a = [1, 2, 3]\nlet s = a[1..4]\n";
+        let e = runtime_error(text);
+        let message = "slice out of range: the end is 4 but the length is 3";
+        assert_eq!(e.message, message);
+        assert_eq!(expr_text(text, e.expr), "1..4");
+
+        let text = "let a = [1, 2, 3]\nlet j = 1\nlet s = a[2..j]\n";
+        let e = runtime_error(text);
+        let message = "slice out of range: the start is 2 but the end is 1";
+        assert_eq!(e.message, message);
+        assert_eq!(expr_text(text, e.expr), "2..j");
+    }
+
+    #[test]
+    fn literal_out_of_range() {
+        assert_eq!(error_text("let a: u8 = 256\n"), "256");
+        assert_eq!(error_text("let a: s8 = 1\na = -129\n"), "-129");
+    }
+
+    #[test]
+    fn labels() {
+        run_on_file("labels.liu", "0 0 \n1 2 \n5 \n");
+    }
+
+    #[test]
+    fn unknown_label() {
+        let text = "outer: for {\n    break inner\n}\n";
+        assert_eq!(error_text(text), "inner");
+    }
+
+    #[test]
+    fn loops() {
+        run_on_file(
+            "loops.liu",
+            "0 \n1 \n2 \n100 \n2 \n4 \n6 \n7 \n1 0 \n2 0 \n1 9 \n",
+        );
+    }
+
+    #[test]
+    fn unary() {
+        run_on_file("unary.liu", "3 true 2 \ntrue false \n12 \n7 \n");
+    }
+
+    #[test]
+    fn bad_unary() {
+        assert_eq!(error_text("let a = 1\nprint(!a)\n"), "!a");
+        assert_eq!(error_text("print(~true)\n"), "~true");
+        assert_eq!(error_text("print(-true)\n"), "-true");
+    }
 
     #[test]
     fn simple() {
-        run_on_file("simple.liu", "69 73 \n69 8491 \n");
+        run_on_file("simple.liu", "69 73 \n69 8491 \n2760 \n");
     }
 
     fn run_on_file(name: &str, expected: &str) {
@@ -86,18 +483,49 @@ mod tests {
         // panic!("viewing");
     }
 
-    fn run_on_file_err(text: &str) -> Result<String, Error> {
+    // The source text that the error from compiling `text` points to
+    fn error_text(text: &str) -> &str {
+        let loc = match run_on_file_err(text) {
+            Ok(_) => panic!("expected an error"),
+            Err(Error::Simple { loc, .. }) => loc,
+            Err(Error::StaticSimple { loc, .. }) => loc,
+        };
+
+        return &text[loc.start as usize..loc.end as usize];
+    }
+
+    fn runtime_error(text: &str) -> IError {
+        let data = expect(compile(text));
+
+        let mut out = String::new();
+        let mut interp = Interpreter::new(data, &mut out);
+        return match interp.run() {
+            Ok(()) => panic!("expected a runtime error"),
+            Err(e) => e,
+        };
+    }
+
+    fn expr_text(text: &str, expr: ExprId) -> &str {
+        let loc = expr.loc();
+        return &text[loc.start as usize..loc.end as usize];
+    }
+
+    fn compile(text: &str) -> Result<AllocTracker, Error> {
         let mut table = StringTable::new();
 
         let data = lex(&mut table, 0, text)?;
 
         let ast = parse(&table, 0, data)?;
 
-        let (graph, entry) = check_ast(&ast)?;
+        let (graph, entry) = check_ast(&table, &ast)?;
+
+        let assembler = Assembler::new();
 
-        let mut assembler = Assembler::new();
+        return Ok(assembler.assemble(&table, &graph, entry));
+    }
 
-        let data = assembler.assemble(&graph, entry);
+    fn run_on_file_err(text: &str) -> Result<String, Error> {
+        let data = compile(text)?;
 
         let mut out = String::new();
         let mut interp = Interpreter::new(data, &mut out);
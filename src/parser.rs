@@ -7,6 +7,8 @@ pub enum Key {
     Let = 0,
     Proc,
     Type,
+    Struct,
+    Enum,
     Defer,
     Context,
 
@@ -17,12 +19,36 @@ pub enum Key {
     Continue,
     Break,
     For,
+    In,
 
     Spawn,
     Wait,
 
+    Return,
+
+    True,
+    False,
+
     Underscore,
     Print,
+    Alloc,
+    Free,
+    Len,
+
+    // Type names; these aren't reserved, so they're after Underscore
+    U8,
+    U16,
+    U32,
+    U64,
+    S8,
+    S16,
+    S32,
+    S64,
+    Bool,
+    F32,
+    F64,
+    Char,
+    String,
 }
 
 impl Key {
@@ -47,6 +73,7 @@ pub enum TokenKind {
     Bang = b'!',
     Tilde = b'~',
     Amp = b'&',
+    Pipe = b'|',
     Caret = b'^',
     Mod = b'%',
     Star = b'*',
@@ -62,8 +89,24 @@ pub enum TokenKind {
     LtEq,         // <=
     GtEq,         // >=
 
-    And, // &&
-    Or,  // ||
+    And,      // &&
+    Or,       // ||
+    LShift,   // <<
+    RShift,   // >>
+    Arrow,    // ->
+    FatArrow, // =>
+    DotDot,   // ..
+
+    PlusEq,   // +=
+    DashEq,   // -=
+    StarEq,   // *=
+    DivEq,    // /=
+    ModEq,    // %=
+    AmpEq,    // &=
+    PipeEq,   // |=
+    CaretEq,  // ^=
+    LShiftEq, // <<=
+    RShiftEq, // >>=
 
     Directive,
     Word,
@@ -94,10 +137,26 @@ impl Token {
             TokenKind::Number => return table.names[self.data].len() as u32,
 
             TokenKind::Equal2 => return 2,
+            TokenKind::NotEqual => return 2,
             TokenKind::LtEq => return 2,
             TokenKind::GtEq => return 2,
             TokenKind::And => return 2,
             TokenKind::Or => return 2,
+            TokenKind::LShift => return 2,
+            TokenKind::RShift => return 2,
+            TokenKind::Arrow => return 2,
+            TokenKind::FatArrow => return 2,
+            TokenKind::DotDot => return 2,
+            TokenKind::PlusEq => return 2,
+            TokenKind::DashEq => return 2,
+            TokenKind::StarEq => return 2,
+            TokenKind::DivEq => return 2,
+            TokenKind::ModEq => return 2,
+            TokenKind::AmpEq => return 2,
+            TokenKind::PipeEq => return 2,
+            TokenKind::CaretEq => return 2,
+            TokenKind::LShiftEq => return 3,
+            TokenKind::RShiftEq => return 3,
 
             _ => return 1,
         }
@@ -243,11 +302,23 @@ impl<'a> Parser<'a> {
             return Ok(expr);
         }
 
+        if let Some(expr) = self.parse_type_decl()? {
+            return Ok(expr);
+        }
+
         if let Some(expr) = self.parse_let()? {
             return Ok(expr);
         }
 
-        if let Some(expr) = self.parse_assign()? {
+        if let Some(expr) = self.parse_return()? {
+            return Ok(expr);
+        }
+
+        if let Some(expr) = self.parse_break()? {
+            return Ok(expr);
+        }
+
+        if let Some(expr) = self.parse_defer()? {
             return Ok(expr);
         }
 
@@ -255,7 +326,7 @@ impl<'a> Parser<'a> {
             return Ok(expr);
         }
 
-        return self.parse_binary_op();
+        return self.parse_assign();
     }
 
     pub fn parse_proc(&mut self) -> Result<Option<Expr>, Error> {
@@ -298,14 +369,49 @@ impl<'a> Parser<'a> {
 
         self.pop_kinds_loop(&[Skip, NewlineSkip]);
 
-        if self.pop_kind(RParen).is_none() {
-            loc.end = self.text_cursor;
+        let mut params = Pod::new();
+        while self.pop_kind(RParen).is_none() {
+            let param = self.parse_param("a parameter name")?;
+            params.push(param);
+
+            let before_comma = self.text_cursor;
 
-            return Err(Error::expected("opening closing parenthesis", loc));
+            self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+            let found_comma = self.pop_kind(Comma).is_some();
+
+            self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+            if self.pop_kind(RParen).is_some() {
+                break;
+            }
+
+            if !found_comma {
+                loc.start = before_comma;
+                loc.end = before_comma;
+
+                return Err(Error::expected("a comma or closing paren", loc));
+            }
         }
 
+        // NOTE: empty Pods can't be deref-ed, because their pointer is unaligned
+        let params = match params.len() {
+            0 => ExprRange::EMPTY,
+            _ => self.allocator.add_slice(&params),
+        };
+
         self.pop_kinds_loop(&[Skip, NewlineSkip]);
 
+        let mut return_type = ExprId::NULL;
+        if self.pop_kind(Arrow).is_some() {
+            self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+            let ty = self.parse_type()?;
+            return_type = self.allocator.make(ty);
+
+            self.pop_kinds_loop(&[Skip, NewlineSkip]);
+        }
+
         let code = match self.parse_control()? {
             Some(e) => e,
             None => {
@@ -317,12 +423,18 @@ impl<'a> Parser<'a> {
 
         let code = self.allocator.make(code);
 
-        let kind = ExprKind::Procedure(Proc { symbol, code });
+        let kind = ExprKind::Procedure(Proc {
+            symbol,
+            params,
+            return_type,
+            code,
+        });
 
         return Ok(Some(Expr { kind, loc }));
     }
 
-    pub fn parse_let(&mut self) -> Result<Option<Expr>, Error> {
+    // `type Point struct { x: s64, y: s64 }` or `type Color enum { Red, Blue }`
+    pub fn parse_type_decl(&mut self) -> Result<Option<Expr>, Error> {
         use TokenKind::*;
 
         let mut loc = CodeLoc {
@@ -331,64 +443,97 @@ impl<'a> Parser<'a> {
             file: self.file,
         };
 
-        if !self.pop_tok(Word, Key::Let as u32) {
+        if !self.pop_tok(Word, Key::Type as u32) {
             return Ok(None);
-        };
+        }
 
-        self.pop_kinds_loop(&[Skip, NewlineSkip]);
+        self.pop_kinds_loop(&[Skip]);
 
-        let ident = match self.pop_kind(Word) {
-            Some(tok) => tok,
-            None => {
+        let symbol = match self.pop_kind(Word) {
+            Some(tok) if tok.data >= Key::COUNT as u32 => tok.data,
+            Some(_) | None => {
                 loc.end = self.text_cursor;
 
-                return Err(Error::expected("an identifer", loc));
+                return Err(Error::expected("a type name", loc));
             }
         };
 
-        if ident.data < Key::COUNT as u32 {
+        self.pop_kinds_loop(&[Skip]);
+
+        let ty = match self.peek() {
+            Some(tok) if tok.kind == Word && tok.data == Key::Enum as u32 => {
+                self.parse_enum_type()?
+            }
+            _ => self.parse_struct_type()?,
+        };
+        loc.end = ty.loc.end;
+
+        let ty = self.allocator.make(ty);
+        let kind = ExprKind::TypeDecl { symbol, ty };
+
+        return Ok(Some(Expr { kind, loc }));
+    }
+
+    pub fn parse_struct_type(&mut self) -> Result<Expr, Error> {
+        use TokenKind::*;
+
+        let mut loc = CodeLoc {
+            start: self.text_cursor,
+            end: self.text_cursor,
+            file: self.file,
+        };
+
+        if !self.pop_tok(Word, Key::Struct as u32) {
             loc.end = self.text_cursor;
 
-            return Err(Error::expected("an identifer", loc));
+            return Err(Error::expected("a struct or enum definition", loc));
         }
 
-        self.pop_kinds_loop(&[Skip, NewlineSkip]);
+        self.pop_kinds_loop(&[Skip]);
 
-        let equal_start = self.text_cursor;
-        match self.pop() {
-            Some(Token { kind: Equal, .. }) => {}
+        if self.pop_kind(LBrace).is_none() {
+            loc.end = self.text_cursor;
 
-            Some(_) | None => {
-                loc.start = equal_start;
-                loc.end = self.text_cursor;
+            return Err(Error::expected("opening brace", loc));
+        }
 
-                return Err(Error::expected("an equal sign", loc));
+        // Structs need at least one field, so that they take up space
+        let mut fields = Pod::new();
+        loop {
+            self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+            let field = self.parse_param("a field name")?;
+            fields.push(field);
+
+            let before_comma = self.text_cursor;
+
+            self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+            let found_comma = self.pop_kind(Comma).is_some();
+
+            self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+            if self.pop_kind(RBrace).is_some() {
+                break;
             }
-        }
 
-        self.pop_kinds_loop(&[Skip, NewlineSkip]);
+            if !found_comma {
+                loc.start = before_comma;
+                loc.end = before_comma;
 
-        let value = match self.parse_control()? {
-            Some(e) => e,
-            None => self.parse_binary_op()?,
-        };
+                return Err(Error::expected("a comma or closing brace", loc));
+            }
+        }
 
-        let value = self.allocator.make(value);
+        let fields = self.allocator.add_slice(&fields);
 
         loc.end = self.text_cursor;
-        let kind = ExprKind::Let {
-            symbol: ident.data,
-            value,
-        };
-
-        return Ok(Some(Expr { kind, loc }));
-    }
+        let kind = ExprKind::StructType { fields };
 
-    pub fn parse_assign(&mut self) -> Result<Option<Expr>, Error> {
-        return Ok(None);
+        return Ok(Expr { kind, loc });
     }
 
-    pub fn parse_control(&mut self) -> Result<Option<Expr>, Error> {
+    pub fn parse_enum_type(&mut self) -> Result<Expr, Error> {
         use TokenKind::*;
 
         let mut loc = CodeLoc {
@@ -397,121 +542,105 @@ impl<'a> Parser<'a> {
             file: self.file,
         };
 
-        // if, else
-        if self.pop_tok(Word, Key::If as u32) {
-            self.pop_kinds_loop(&[Skip, NewlineSkip]);
+        self.pop_tok(Word, Key::Enum as u32);
 
-            let cond = self.parse_binary_op()?;
-            let cond = self.allocator.make(cond);
+        self.pop_kinds_loop(&[Skip]);
 
-            let control_start = self.text_cursor;
-            let if_true = match self.parse_control()? {
-                Some(e) => self.allocator.make(e),
-                None => {
-                    loc.start = control_start;
-                    loc.end = self.text_cursor;
+        if self.pop_kind(LBrace).is_none() {
+            loc.end = self.text_cursor;
 
-                    return Err(Error::expected("control flow or block", loc));
-                }
-            };
+            return Err(Error::expected("opening brace", loc));
+        }
 
-            if !self.pop_tok(Word, Key::Else as u32) {
-                loc.end = self.text_cursor;
-                let kind = ExprKind::If { cond, if_true };
+        let mut variants = Pod::new();
+        loop {
+            self.pop_kinds_loop(&[Skip, NewlineSkip]);
 
-                return Ok(Some(Expr { kind, loc }));
-            }
+            let mut variant_loc = CodeLoc {
+                start: self.text_cursor,
+                end: self.text_cursor,
+                file: self.file,
+            };
 
-            let control_start = self.text_cursor;
-            let if_false = match self.parse_control()? {
-                Some(e) => self.allocator.make(e),
-                None => {
-                    loc.start = control_start;
-                    loc.end = self.text_cursor;
+            let symbol = match self.pop_kind(Word) {
+                Some(tok) if tok.data >= Key::COUNT as u32 => tok.data,
+                Some(_) | None => {
+                    variant_loc.end = self.text_cursor;
 
-                    return Err(Error::expected("control flow or block", loc));
+                    return Err(Error::expected("a variant name", variant_loc));
                 }
             };
 
-            loc.end = self.text_cursor;
-            let kind = ExprKind::IfElse {
-                cond,
-                if_true,
-                if_false,
-            };
-
-            return Ok(Some(Expr { kind, loc }));
-        }
+            // Variants can have a payload, like `Rect(f64, f64)`
+            let mut payload = ExprRange::EMPTY;
+            if self.pop_kind(LParen).is_some() {
+                let mut types = Pod::new();
+                loop {
+                    self.pop_kinds_loop(&[Skip, NewlineSkip]);
 
-        // case
+                    let ty = self.parse_type()?;
+                    types.push(ty);
 
-        // for
+                    let before_comma = self.text_cursor;
 
-        // block
-        if let Some(_) = self.pop_kind(LBrace) {
-            use TokenKind::*;
+                    self.pop_kinds_loop(&[Skip, NewlineSkip]);
 
-            let mut stmts = Pod::new();
+                    let found_comma = self.pop_kind(Comma).is_some();
 
-            self.pop_kinds_loop(&[Skip, NewlineSkip, Semicolon]);
+                    self.pop_kinds_loop(&[Skip, NewlineSkip]);
 
-            if self.pop_kind(RBrace).is_some() {
-                loc.end = self.text_cursor;
+                    if self.pop_kind(RParen).is_some() {
+                        break;
+                    }
 
-                let block = Block {
-                    stmts: ExprRange::EMPTY,
-                };
+                    if !found_comma {
+                        loc.start = before_comma;
+                        loc.end = before_comma;
 
-                let kind = ExprKind::Block(block);
+                        return Err(Error::expected("a comma or closing paren", loc));
+                    }
+                }
 
-                return Ok(Some(Expr { kind, loc }));
+                payload = self.allocator.add_slice(&types);
             }
 
-            // TODO track indentation of braces, for nice reporting
-            // of matching closing braces.
-            //                         - Albert Liu, Mar 04, 2022 Fri 01:14 EST
-            loop {
-                let stmt = self.parse_expr()?;
-                stmts.push(stmt);
-
-                self.pop_kind(Skip);
-
-                let before_eat = self.index;
-
-                self.pop_kinds_loop(&[NewlineSkip, Semicolon]);
+            variant_loc.end = self.text_cursor;
+            let kind = ExprKind::Variant { symbol, payload };
+            variants.push(Expr {
+                kind,
+                loc: variant_loc,
+            });
 
-                if self.pop_kind(RBrace).is_some() {
-                    loc.end = self.text_cursor;
+            let before_comma = self.text_cursor;
 
-                    break;
-                }
+            self.pop_kinds_loop(&[Skip, NewlineSkip]);
 
-                if self.index == before_eat {
-                    loc.end = self.text_cursor;
+            let found_comma = self.pop_kind(Comma).is_some();
 
-                    return Err(Error::expected("a newline or semicolon", loc));
-                }
+            self.pop_kinds_loop(&[Skip, NewlineSkip]);
 
-                self.pop_kinds_loop(&[Skip, NewlineSkip, Semicolon]);
+            if self.pop_kind(RBrace).is_some() {
+                break;
             }
 
-            let stmts = self.allocator.add_slice(&stmts);
-
-            let block = Block { stmts };
-
-            let kind = ExprKind::Block(block);
+            if !found_comma {
+                loc.start = before_comma;
+                loc.end = before_comma;
 
-            return Ok(Some(Expr { kind, loc }));
+                return Err(Error::expected("a comma or closing brace", loc));
+            }
         }
 
-        return Ok(None);
-    }
+        let variants = self.allocator.add_slice(&variants);
 
-    pub fn parse_binary_op(&mut self) -> Result<Expr, Error> {
-        return self.parse_binary_precedence_op(0);
+        loc.end = self.text_cursor;
+        let kind = ExprKind::EnumType { variants };
+
+        return Ok(Expr { kind, loc });
     }
 
-    pub fn parse_binary_precedence_op(&mut self, min_level: u8) -> Result<Expr, Error> {
+    // `name: type`, for parameters and struct fields
+    pub fn parse_param(&mut self, name_kind: &'static str) -> Result<Expr, Error> {
         use TokenKind::*;
 
         let mut loc = CodeLoc {
@@ -520,56 +649,1019 @@ impl<'a> Parser<'a> {
             file: self.file,
         };
 
-        let mut expr = self.parse_prefix()?;
-
-        self.pop_kinds_loop(&[Skip]);
+        let symbol = match self.pop_kind(Word) {
+            Some(tok) if tok.data >= Key::COUNT as u32 => tok.data,
+            Some(_) | None => {
+                loc.end = self.text_cursor;
 
-        // https://eli.thegreenplace.net/2012/08/02/parsing-expressions-by-precedence-climbing
-        // This algorithm is supposed to be efficient. No idea if that's actually true,
-        // but it is incredibly concise.
-        while let Some(tok) = self.peek() {
-            let info = OPERATORS[tok.kind as usize];
-            if info.precedence < min_level {
-                break;
+                return Err(Error::expected(name_kind, loc));
             }
+        };
 
-            let kind = match info.op_kind {
-                Some(kind) => kind,
-                None => break,
-            };
+        self.pop_kinds_loop(&[Skip, NewlineSkip]);
 
-            self.adv();
+        if self.pop_kind(Colon).is_none() {
+            loc.end = self.text_cursor;
 
-            let mut next_min_level = info.precedence;
-            if info.is_left_to_right {
-                next_min_level += 1;
-            }
+            return Err(Error::expected("a colon", loc));
+        }
 
-            self.pop_kinds_loop(&[Skip, NewlineSkip]);
+        self.pop_kinds_loop(&[Skip, NewlineSkip]);
 
-            let right = self.parse_binary_precedence_op(next_min_level)?;
+        let ty = self.parse_type()?;
+        let ty = self.allocator.make(ty);
 
-            if let Some(check) = info.check_operands {
-                check(&expr, &right)?;
+        loc.end = self.text_cursor;
+        let kind = ExprKind::Param { symbol, ty };
+
+        return Ok(Expr { kind, loc });
+    }
+
+    // Integers look like 1_000, 0xff, 0b1010, 0o17, or 10u8
+    // Floats are only decimal, and always have a digit on both sides of the
+    // decimal point, e.g. `1.5` or `2.0f32`
+    fn parse_float(&mut self, text: &str, loc: CodeLoc) -> Result<ExprKind, Error> {
+        let bytes = text.as_bytes();
+
+        let mut digits = String::new();
+        let mut index = 0;
+
+        while let Some(&b) = bytes.get(index) {
+            match b {
+                b'_' => {}
+                b'0'..=b'9' | b'.' => digits.push(b as char),
+
+                // The start of a type suffix
+                b'f' => break,
+
+                _ => {
+                    let start = loc.start + index as u32;
+                    let loc = CodeLoc {
+                        start,
+                        end: start + 1,
+                        file: loc.file,
+                    };
+
+                    return Err(Error::expected("a digit", loc));
+                }
             }
 
-            loc.end = right.loc.end;
+            index += 1;
+        }
 
-            let left = self.allocator.make(expr);
-            let right = self.allocator.make(right);
+        let value = match digits.parse::<f64>() {
+            Ok(value) => value,
+            Err(_) => return Err(Error::expected("a decimal number", loc)),
+        };
 
-            let kind = ExprKind::BinaryOp { kind, left, right };
+        let mut suffix = ExprId::NULL;
+        if index < bytes.len() {
+            let suffix_loc = CodeLoc {
+                start: loc.start + index as u32,
+                end: loc.end,
+                file: loc.file,
+            };
 
-            expr = Expr { kind, loc };
+            let key = match &text[index..] {
+                "f32" => Key::F32,
+                "f64" => Key::F64,
+                _ => return Err(Error::expected("a float type suffix", suffix_loc)),
+            };
 
-            self.pop_kinds_loop(&[Skip]);
+            let kind = ExprKind::Ident { symbol: key as u32 };
+            suffix = self.allocator.make(Expr {
+                kind,
+                loc: suffix_loc,
+            });
         }
 
-        return Ok(expr);
+        return Ok(ExprKind::Float { value, suffix });
     }
 
-    pub fn parse_prefix(&mut self) -> Result<Expr, Error> {
-        use TokenKind::*;
+    fn parse_integer(&mut self, text: &str, loc: CodeLoc) -> Result<ExprKind, Error> {
+        let bytes = text.as_bytes();
+
+        let (base, name, mut index) = match bytes {
+            [b'0', b'x', ..] => (16, "a hexadecimal digit", 2),
+            [b'0', b'o', ..] => (8, "an octal digit", 2),
+            [b'0', b'b', ..] => (2, "a binary digit", 2),
+            _ => (10, "a digit", 0),
+        };
+
+        let mut total: u64 = 0;
+        let mut digit_count = 0;
+
+        while let Some(&b) = bytes.get(index) {
+            let digit = match b {
+                b'_' => {
+                    index += 1;
+                    continue;
+                }
+
+                // The start of a type suffix
+                b'u' | b's' => break,
+                b'f' if base == 10 => break,
+
+                b'0'..=b'9' => (b - b'0') as u64,
+                b'a'..=b'f' if base == 16 => (b - b'a' + 10) as u64,
+                b'A'..=b'F' if base == 16 => (b - b'A' + 10) as u64,
+                _ => base,
+            };
+
+            if digit >= base {
+                let start = loc.start + index as u32;
+                let loc = CodeLoc {
+                    start,
+                    end: start + 1,
+                    file: loc.file,
+                };
+
+                return Err(Error::expected(name, loc));
+            }
+
+            total = match total.checked_mul(base).and_then(|t| t.checked_add(digit)) {
+                Some(total) => total,
+                None => return Err(Error::new("integer is too large", loc)),
+            };
+
+            digit_count += 1;
+            index += 1;
+        }
+
+        if digit_count == 0 {
+            return Err(Error::expected(name, loc));
+        }
+
+        let mut suffix = ExprId::NULL;
+        if index < bytes.len() {
+            let suffix_loc = CodeLoc {
+                start: loc.start + index as u32,
+                end: loc.end,
+                file: loc.file,
+            };
+
+            let key = match &text[index..] {
+                "u8" => Key::U8,
+                "u16" => Key::U16,
+                "u32" => Key::U32,
+                "u64" => Key::U64,
+                "s8" => Key::S8,
+                "s16" => Key::S16,
+                "s32" => Key::S32,
+                "s64" => Key::S64,
+
+                // Things like `1f32` are floats
+                "f32" | "f64" if base == 10 => {
+                    let key = if &text[index..] == "f32" {
+                        Key::F32
+                    } else {
+                        Key::F64
+                    };
+                    let kind = ExprKind::Ident { symbol: key as u32 };
+                    let suffix = self.allocator.make(Expr {
+                        kind,
+                        loc: suffix_loc,
+                    });
+
+                    let value = total as f64;
+                    return Ok(ExprKind::Float { value, suffix });
+                }
+
+                _ => return Err(Error::expected("an integer type suffix", suffix_loc)),
+            };
+
+            let kind = ExprKind::Ident { symbol: key as u32 };
+            suffix = self.allocator.make(Expr {
+                kind,
+                loc: suffix_loc,
+            });
+        }
+
+        return Ok(ExprKind::Integer {
+            value: total,
+            suffix,
+        });
+    }
+
+    pub fn parse_type(&mut self) -> Result<Expr, Error> {
+        use TokenKind::*;
+
+        let mut loc = CodeLoc {
+            start: self.text_cursor,
+            end: self.text_cursor,
+            file: self.file,
+        };
+
+        if self.pop_kind(Star).is_some() {
+            self.pop_kinds_loop(&[Skip]);
+
+            let pointee = self.parse_type()?;
+            loc.end = pointee.loc.end;
+
+            let pointee = self.allocator.make(pointee);
+            let kind = ExprKind::PointerType { pointee };
+
+            return Ok(Expr { kind, loc });
+        }
+
+        // `[]T` is a slice, and `[N]T` is an array
+        if self.pop_kind(LBracket).is_some() {
+            self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+            let mut len = ExprId::NULL;
+            if self.pop_kind(RBracket).is_none() {
+                let expr = self.parse_binary_op()?;
+                len = self.allocator.make(expr);
+
+                self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+                if self.pop_kind(RBracket).is_none() {
+                    loc.end = self.text_cursor;
+
+                    return Err(Error::expected("a closing bracket", loc));
+                }
+            }
+
+            self.pop_kinds_loop(&[Skip]);
+
+            let elem = self.parse_type()?;
+            loc.end = elem.loc.end;
+
+            let elem = self.allocator.make(elem);
+            let kind = match len.is_null() {
+                true => ExprKind::SliceType { elem },
+                false => ExprKind::ArrayType { len, elem },
+            };
+
+            return Ok(Expr { kind, loc });
+        }
+
+        match self.pop_kind(Word) {
+            Some(tok) if tok.data >= Key::COUNT as u32 => {
+                loc.end = self.text_cursor;
+                let kind = ExprKind::Ident { symbol: tok.data };
+
+                return Ok(Expr { kind, loc });
+            }
+
+            Some(_) | None => {
+                loc.end = self.text_cursor;
+
+                return Err(Error::expected("a type", loc));
+            }
+        }
+    }
+
+    pub fn parse_break(&mut self) -> Result<Option<Expr>, Error> {
+        use TokenKind::*;
+
+        let mut loc = CodeLoc {
+            start: self.text_cursor,
+            end: self.text_cursor,
+            file: self.file,
+        };
+
+        let is_break = if self.pop_tok(Word, Key::Break as u32) {
+            true
+        } else if self.pop_tok(Word, Key::Continue as u32) {
+            false
+        } else {
+            return Ok(None);
+        };
+
+        loc.end = self.text_cursor;
+
+        self.pop_kinds_loop(&[Skip]);
+
+        let mut label = ExprId::NULL;
+        if let Some(tok) = self.peek() {
+            if tok.kind == Word && tok.data >= Key::COUNT as u32 {
+                let label_loc = self.parse_label();
+                loc.end = label_loc.end;
+
+                let kind = ExprKind::Ident { symbol: tok.data };
+                label = self.allocator.make(Expr {
+                    kind,
+                    loc: label_loc,
+                });
+            }
+        }
+
+        let kind = match is_break {
+            true => ExprKind::Break { label },
+            false => ExprKind::Continue { label },
+        };
+
+        return Ok(Some(Expr { kind, loc }));
+    }
+
+    pub fn parse_defer(&mut self) -> Result<Option<Expr>, Error> {
+        use TokenKind::*;
+
+        let mut loc = CodeLoc {
+            start: self.text_cursor,
+            end: self.text_cursor,
+            file: self.file,
+        };
+
+        if !self.pop_tok(Word, Key::Defer as u32) {
+            return Ok(None);
+        }
+
+        self.pop_kinds_loop(&[Skip]);
+
+        let value = self.parse_value()?;
+        let value = self.allocator.make(value);
+
+        loc.end = self.text_cursor;
+
+        let kind = ExprKind::Defer { value };
+
+        return Ok(Some(Expr { kind, loc }));
+    }
+
+    pub fn parse_return(&mut self) -> Result<Option<Expr>, Error> {
+        use TokenKind::*;
+
+        let mut loc = CodeLoc {
+            start: self.text_cursor,
+            end: self.text_cursor,
+            file: self.file,
+        };
+
+        if !self.pop_tok(Word, Key::Return as u32) {
+            return Ok(None);
+        }
+
+        loc.end = self.text_cursor;
+
+        self.pop_kinds_loop(&[Skip]);
+
+        let mut value = ExprId::NULL;
+        match self.peek() {
+            None => {}
+            Some(Token {
+                kind: NewlineSkip | Semicolon | RBrace,
+                ..
+            }) => {}
+
+            Some(_) => {
+                let expr = self.parse_value()?;
+                loc.end = expr.loc.end;
+
+                value = self.allocator.make(expr);
+            }
+        }
+
+        let kind = ExprKind::Return { value };
+
+        return Ok(Some(Expr { kind, loc }));
+    }
+
+    pub fn parse_let(&mut self) -> Result<Option<Expr>, Error> {
+        use TokenKind::*;
+
+        let mut loc = CodeLoc {
+            start: self.text_cursor,
+            end: self.text_cursor,
+            file: self.file,
+        };
+
+        if !self.pop_tok(Word, Key::Let as u32) {
+            return Ok(None);
+        };
+
+        self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+        let ident = match self.pop_kind(Word) {
+            Some(tok) => tok,
+            None => {
+                loc.end = self.text_cursor;
+
+                return Err(Error::expected("an identifer", loc));
+            }
+        };
+
+        if ident.data < Key::COUNT as u32 {
+            loc.end = self.text_cursor;
+
+            return Err(Error::expected("an identifer", loc));
+        }
+
+        self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+        let mut ty = ExprId::NULL;
+        if self.pop_kind(Colon).is_some() {
+            self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+            let expr = self.parse_type()?;
+            ty = self.allocator.make(expr);
+
+            self.pop_kinds_loop(&[Skip, NewlineSkip]);
+        }
+
+        let equal_start = self.text_cursor;
+        match self.pop() {
+            Some(Token { kind: Equal, .. }) => {}
+
+            Some(_) | None => {
+                loc.start = equal_start;
+                loc.end = self.text_cursor;
+
+                return Err(Error::expected("an equal sign", loc));
+            }
+        }
+
+        self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+        let value = self.parse_value()?;
+
+        let value = self.allocator.make(value);
+
+        loc.end = self.text_cursor;
+        let kind = ExprKind::Let {
+            symbol: ident.data,
+            ty,
+            value,
+        };
+
+        return Ok(Some(Expr { kind, loc }));
+    }
+
+    // Values that get stored somewhere, like in a `let` or a `return`, can
+    // also be an if-else or a match
+    pub fn parse_value(&mut self) -> Result<Expr, Error> {
+        if let Some(expr) = self.parse_control()? {
+            return Ok(expr);
+        }
+
+        return self.parse_binary_op();
+    }
+
+    pub fn parse_assign(&mut self) -> Result<Expr, Error> {
+        use TokenKind::*;
+
+        let mut loc = CodeLoc {
+            start: self.text_cursor,
+            end: self.text_cursor,
+            file: self.file,
+        };
+
+        let target = self.parse_binary_op()?;
+
+        self.pop_kinds_loop(&[Skip]);
+
+        let op_kind = match self.peek() {
+            Some(Token { kind: Equal, .. }) => None,
+            Some(Token { kind: PlusEq, .. }) => Some(BinaryExprKind::Add),
+            Some(Token { kind: DashEq, .. }) => Some(BinaryExprKind::Subtract),
+            Some(Token { kind: StarEq, .. }) => Some(BinaryExprKind::Multiply),
+            Some(Token { kind: DivEq, .. }) => Some(BinaryExprKind::Divide),
+            Some(Token { kind: ModEq, .. }) => Some(BinaryExprKind::Modulo),
+            Some(Token { kind: AmpEq, .. }) => Some(BinaryExprKind::BitAnd),
+            Some(Token { kind: PipeEq, .. }) => Some(BinaryExprKind::BitOr),
+            Some(Token { kind: CaretEq, .. }) => Some(BinaryExprKind::BitXor),
+            Some(Token { kind: LShiftEq, .. }) => Some(BinaryExprKind::ShiftLeft),
+            Some(Token { kind: RShiftEq, .. }) => Some(BinaryExprKind::ShiftRight),
+            _ => return Ok(target),
+        };
+
+        self.adv();
+
+        self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+        let value = self.parse_value()?;
+        loc.end = value.loc.end;
+
+        let target = self.allocator.make(target);
+        let value = self.allocator.make(value);
+
+        let kind = match op_kind {
+            Some(kind) => ExprKind::CompoundAssign {
+                kind,
+                target,
+                value,
+            },
+            None => ExprKind::Assign { target, value },
+        };
+
+        return Ok(Expr { kind, loc });
+    }
+
+    pub fn parse_control(&mut self) -> Result<Option<Expr>, Error> {
+        use TokenKind::*;
+
+        let mut loc = CodeLoc {
+            start: self.text_cursor,
+            end: self.text_cursor,
+            file: self.file,
+        };
+
+        // if, else
+        if self.pop_tok(Word, Key::If as u32) {
+            self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+            let cond = self.parse_binary_op()?;
+            let cond = self.allocator.make(cond);
+
+            let control_start = self.text_cursor;
+            let if_true = match self.parse_control()? {
+                Some(e) => self.allocator.make(e),
+                None => {
+                    loc.start = control_start;
+                    loc.end = self.text_cursor;
+
+                    return Err(Error::expected("control flow or block", loc));
+                }
+            };
+
+            loc.end = self.text_cursor;
+
+            // Only eat the whitespace if there's actually an else; otherwise
+            // the newline is needed to end the statement
+            let (index, text_cursor) = (self.index, self.text_cursor);
+            self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+            if !self.pop_tok(Word, Key::Else as u32) {
+                self.index = index;
+                self.text_cursor = text_cursor;
+
+                let kind = ExprKind::If { cond, if_true };
+
+                return Ok(Some(Expr { kind, loc }));
+            }
+
+            self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+            let control_start = self.text_cursor;
+            let if_false = match self.parse_control()? {
+                Some(e) => self.allocator.make(e),
+                None => {
+                    loc.start = control_start;
+                    loc.end = self.text_cursor;
+
+                    return Err(Error::expected("control flow or block", loc));
+                }
+            };
+
+            loc.end = self.text_cursor;
+            let kind = ExprKind::IfElse {
+                cond,
+                if_true,
+                if_false,
+            };
+
+            return Ok(Some(Expr { kind, loc }));
+        }
+
+        // match
+        if self.pop_tok(Word, Key::Match as u32) {
+            self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+            let value = self.parse_binary_op()?;
+            let value = self.allocator.make(value);
+
+            self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+            if self.pop_kind(LBrace).is_none() {
+                loc.end = self.text_cursor;
+
+                return Err(Error::expected("opening brace", loc));
+            }
+
+            let mut arms = Pod::new();
+
+            self.pop_kinds_loop(&[Skip, NewlineSkip, Semicolon]);
+
+            while self.pop_kind(RBrace).is_none() {
+                let arm = self.parse_match_arm()?;
+                arms.push(arm);
+
+                self.pop_kind(Skip);
+
+                let before_eat = self.index;
+
+                self.pop_kinds_loop(&[NewlineSkip, Semicolon, Comma]);
+
+                if self.pop_kind(RBrace).is_some() {
+                    break;
+                }
+
+                if self.index == before_eat {
+                    loc.end = self.text_cursor;
+
+                    return Err(Error::expected("a newline or comma", loc));
+                }
+
+                self.pop_kinds_loop(&[Skip, NewlineSkip, Semicolon]);
+            }
+
+            let arms = match arms.len() {
+                0 => ExprRange::EMPTY,
+                _ => self.allocator.add_slice(&arms),
+            };
+
+            loc.end = self.text_cursor;
+            let kind = ExprKind::Match { value, arms };
+
+            return Ok(Some(Expr { kind, loc }));
+        }
+
+        // label: for
+        let mut label = ExprId::NULL;
+        if let Some(tok) = self.peek() {
+            let (index, text_cursor) = (self.index, self.text_cursor);
+
+            if tok.kind == Word && tok.data >= Key::COUNT as u32 {
+                let label_loc = self.parse_label();
+                self.pop_kinds_loop(&[Skip]);
+
+                if self.pop_kind(Colon).is_some() {
+                    self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+                    let kind = ExprKind::Ident { symbol: tok.data };
+                    label = self.allocator.make(Expr {
+                        kind,
+                        loc: label_loc,
+                    });
+
+                    if !self.pop_tok(Word, Key::For as u32) {
+                        loc.start = self.text_cursor;
+                        loc.end = self.text_cursor;
+
+                        return Err(Error::expected("a loop after the label", loc));
+                    }
+                } else {
+                    self.index = index;
+                    self.text_cursor = text_cursor;
+                }
+            }
+        }
+
+        // for
+        if !label.is_null() || self.pop_tok(Word, Key::For as u32) {
+            self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+            let kind = self.parse_for_header()?;
+
+            let control_start = self.text_cursor;
+            let body = match self.parse_control()? {
+                Some(e) => self.allocator.make(e),
+                None => {
+                    loc.start = control_start;
+                    loc.end = self.text_cursor;
+
+                    return Err(Error::expected("a block", loc));
+                }
+            };
+
+            loc.end = self.text_cursor;
+
+            let kind = match kind {
+                ForHeader::Infinite => ExprKind::ForInfinite { label, body },
+                ForHeader::Cond(cond) => ExprKind::ForCond { label, cond, body },
+                ForHeader::Range(symbol, range) => ExprKind::ForRange {
+                    label,
+                    symbol,
+                    range,
+                    body,
+                },
+            };
+
+            return Ok(Some(Expr { kind, loc }));
+        }
+
+        // block
+        if self.pop_kind(LBrace).is_some() {
+            use TokenKind::*;
+
+            let mut stmts = Pod::new();
+
+            self.pop_kinds_loop(&[Skip, NewlineSkip, Semicolon]);
+
+            if self.pop_kind(RBrace).is_some() {
+                loc.end = self.text_cursor;
+
+                let block = Block {
+                    stmts: ExprRange::EMPTY,
+                };
+
+                let kind = ExprKind::Block(block);
+
+                return Ok(Some(Expr { kind, loc }));
+            }
+
+            // TODO track indentation of braces, for nice reporting
+            // of matching closing braces.
+            //                         - Albert Liu, Mar 04, 2022 Fri 01:14 EST
+            loop {
+                let stmt = self.parse_expr()?;
+                stmts.push(stmt);
+
+                self.pop_kind(Skip);
+
+                let before_eat = self.index;
+
+                self.pop_kinds_loop(&[NewlineSkip, Semicolon]);
+
+                if self.pop_kind(RBrace).is_some() {
+                    loc.end = self.text_cursor;
+
+                    break;
+                }
+
+                if self.index == before_eat {
+                    loc.end = self.text_cursor;
+
+                    return Err(Error::expected("a newline or semicolon", loc));
+                }
+
+                self.pop_kinds_loop(&[Skip, NewlineSkip, Semicolon]);
+            }
+
+            let stmts = self.allocator.add_slice(&stmts);
+
+            let block = Block { stmts };
+
+            let kind = ExprKind::Block(block);
+
+            return Ok(Some(Expr { kind, loc }));
+        }
+
+        return Ok(None);
+    }
+
+    // `pattern => expr`
+    fn parse_match_arm(&mut self) -> Result<Expr, Error> {
+        use TokenKind::*;
+
+        let mut loc = CodeLoc {
+            start: self.text_cursor,
+            end: self.text_cursor,
+            file: self.file,
+        };
+
+        let pattern = self.parse_pattern()?;
+        let pattern = self.allocator.make(pattern);
+
+        self.pop_kinds_loop(&[Skip]);
+
+        if self.pop_kind(FatArrow).is_none() {
+            loc.start = self.text_cursor;
+            loc.end = self.text_cursor;
+
+            return Err(Error::expected("an arrow", loc));
+        }
+
+        self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+        let body = self.parse_expr()?;
+        let body = self.allocator.make(body);
+
+        loc.end = self.text_cursor;
+        let kind = ExprKind::MatchArm { pattern, body };
+
+        return Ok(Expr { kind, loc });
+    }
+
+    // Patterns are `_`, which matches anything, a name, which matches
+    // anything and declares a variable with the value, or a variant like
+    // `Color.Red` or `Shape.Circle(r)`, whose payload is more patterns
+    fn parse_pattern(&mut self) -> Result<Expr, Error> {
+        use TokenKind::*;
+
+        let mut loc = CodeLoc {
+            start: self.text_cursor,
+            end: self.text_cursor,
+            file: self.file,
+        };
+
+        let symbol = match self.pop_kind(Word) {
+            Some(tok) if tok.data >= Key::COUNT as u32 => tok.data,
+            Some(tok) if tok.data == Key::Underscore as u32 => tok.data,
+            Some(_) | None => {
+                loc.end = self.text_cursor;
+
+                return Err(Error::expected("a pattern", loc));
+            }
+        };
+
+        loc.end = self.text_cursor;
+        let kind = ExprKind::Ident { symbol };
+        let name = Expr { kind, loc };
+
+        self.pop_kinds_loop(&[Skip]);
+
+        if symbol == Key::Underscore as u32 || self.pop_kind(Dot).is_none() {
+            return Ok(name);
+        }
+
+        let symbol = match self.pop_kind(Word) {
+            Some(tok) if tok.data >= Key::COUNT as u32 => tok.data,
+            Some(_) | None => {
+                loc.start = self.text_cursor;
+                loc.end = self.text_cursor;
+
+                return Err(Error::expected("a variant name", loc));
+            }
+        };
+
+        let value = self.allocator.make(name);
+
+        loc.end = self.text_cursor;
+        let kind = ExprKind::Field { value, symbol };
+        let variant = Expr { kind, loc };
+
+        self.pop_kinds_loop(&[Skip]);
+
+        if self.pop_kind(LParen).is_none() {
+            return Ok(variant);
+        }
+
+        let mut args = Pod::new();
+        loop {
+            self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+            let arg = self.parse_pattern()?;
+            args.push(arg);
+
+            let before_comma = self.text_cursor;
+
+            self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+            let found_comma = self.pop_kind(Comma).is_some();
+
+            self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+            if self.pop_kind(RParen).is_some() {
+                break;
+            }
+
+            if !found_comma {
+                loc.start = before_comma;
+                loc.end = before_comma;
+
+                return Err(Error::expected("a comma or closing paren", loc));
+            }
+        }
+
+        let callee = self.allocator.make(variant);
+        let args = self.allocator.add_slice(&args);
+
+        loc.end = self.text_cursor;
+        let kind = ExprKind::Call { callee, args };
+
+        return Ok(Expr { kind, loc });
+    }
+
+    // Pops the label's word token and returns its location
+    fn parse_label(&mut self) -> CodeLoc {
+        let start = self.text_cursor;
+        self.pop_kind(TokenKind::Word);
+
+        return CodeLoc {
+            start,
+            end: self.text_cursor,
+            file: self.file,
+        };
+    }
+
+    fn parse_for_header(&mut self) -> Result<ForHeader, Error> {
+        use TokenKind::*;
+
+        if let Some(Token { kind: LBrace, .. }) = self.peek() {
+            return Ok(ForHeader::Infinite);
+        }
+
+        // for i in a..b
+        let (index, text_cursor) = (self.index, self.text_cursor);
+        if let Some(tok) = self.pop_kind(Word) {
+            self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+            if self.pop_tok(Word, Key::In as u32) {
+                let mut loc = CodeLoc {
+                    start: text_cursor,
+                    end: self.text_cursor,
+                    file: self.file,
+                };
+
+                if tok.data < Key::COUNT as u32 {
+                    return Err(Error::expected("a loop variable name", loc));
+                }
+
+                self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+                let range = self.parse_range()?;
+                let range = self.allocator.make(range);
+
+                return Ok(ForHeader::Range(tok.data, range));
+            }
+
+            self.index = index;
+            self.text_cursor = text_cursor;
+        }
+
+        let cond = self.parse_binary_op()?;
+        let cond = self.allocator.make(cond);
+
+        return Ok(ForHeader::Cond(cond));
+    }
+
+    pub fn parse_range(&mut self) -> Result<Expr, Error> {
+        use TokenKind::*;
+
+        let mut loc = CodeLoc {
+            start: self.text_cursor,
+            end: self.text_cursor,
+            file: self.file,
+        };
+
+        let start = self.parse_binary_op()?;
+        let start = self.allocator.make(start);
+
+        self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+        if self.pop_kind(DotDot).is_none() {
+            loc.end = self.text_cursor;
+
+            return Err(Error::expected("a range", loc));
+        }
+
+        self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+        let end = self.parse_binary_op()?;
+        loc.end = end.loc.end;
+
+        let end = self.allocator.make(end);
+
+        let kind = ExprKind::Range { start, end };
+
+        return Ok(Expr { kind, loc });
+    }
+
+    pub fn parse_binary_op(&mut self) -> Result<Expr, Error> {
+        return self.parse_binary_precedence_op(0);
+    }
+
+    pub fn parse_binary_precedence_op(&mut self, min_level: u8) -> Result<Expr, Error> {
+        use TokenKind::*;
+
+        let mut loc = CodeLoc {
+            start: self.text_cursor,
+            end: self.text_cursor,
+            file: self.file,
+        };
+
+        let mut expr = self.parse_prefix()?;
+
+        self.pop_kinds_loop(&[Skip]);
+
+        // https://eli.thegreenplace.net/2012/08/02/parsing-expressions-by-precedence-climbing
+        // This algorithm is supposed to be efficient. No idea if that's actually true,
+        // but it is incredibly concise.
+        while let Some(tok) = self.peek() {
+            let info = OPERATORS[tok.kind as usize];
+            if info.precedence < min_level {
+                break;
+            }
+
+            let kind = match info.op_kind {
+                Some(kind) => kind,
+                None => break,
+            };
+
+            self.adv();
+
+            let mut next_min_level = info.precedence;
+            if info.is_left_to_right {
+                next_min_level += 1;
+            }
+
+            self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+            let right = self.parse_binary_precedence_op(next_min_level)?;
+
+            if let Some(check) = info.check_operands {
+                check(&expr, &right)?;
+            }
+
+            loc.end = right.loc.end;
+
+            let left = self.allocator.make(expr);
+            let right = self.allocator.make(right);
+
+            let kind = ExprKind::BinaryOp { kind, left, right };
+
+            expr = Expr { kind, loc };
+
+            self.pop_kinds_loop(&[Skip]);
+        }
+
+        return Ok(expr);
+    }
+
+    pub fn parse_prefix(&mut self) -> Result<Expr, Error> {
+        use TokenKind::*;
 
         let mut loc = CodeLoc {
             start: self.text_cursor,
@@ -577,7 +1669,26 @@ impl<'a> Parser<'a> {
             file: self.file,
         };
 
-        return self.parse_postfix();
+        let kind = match self.peek() {
+            Some(Token { kind: Dash, .. }) => UnaryExprKind::Negate,
+            Some(Token { kind: Bang, .. }) => UnaryExprKind::BoolNot,
+            Some(Token { kind: Tilde, .. }) => UnaryExprKind::BitNot,
+            Some(Token { kind: Amp, .. }) => UnaryExprKind::Ref,
+            Some(Token { kind: Star, .. }) => UnaryExprKind::Deref,
+            _ => return self.parse_postfix(),
+        };
+
+        self.adv();
+
+        self.pop_kinds_loop(&[Skip]);
+
+        let value = self.parse_prefix()?;
+        loc.end = value.loc.end;
+
+        let value = self.allocator.make(value);
+        let kind = ExprKind::UnaryOp { kind, value };
+
+        return Ok(Expr { kind, loc });
     }
 
     pub fn parse_postfix(&mut self) -> Result<Expr, Error> {
@@ -603,48 +1714,118 @@ impl<'a> Parser<'a> {
                     if self.pop_kind(RParen).is_some() {
                         loc.end = self.text_cursor;
 
-                        let callee = self.allocator.make(expr);
-                        let kind = ExprKind::Call {
-                            callee,
-                            args: ExprRange::EMPTY,
-                        };
+                        let callee = self.allocator.make(expr);
+                        let kind = ExprKind::Call {
+                            callee,
+                            args: ExprRange::EMPTY,
+                        };
+
+                        expr = Expr { kind, loc };
+                        continue;
+                    }
+
+                    let mut args = Pod::new();
+                    loop {
+                        let expr = self.parse_binary_op()?;
+                        args.push(expr);
+
+                        let before_comma = self.text_cursor;
+
+                        self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+                        let found_comma = self.pop_kind(Comma).is_some();
+
+                        self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+                        if self.pop_kind(RParen).is_some() {
+                            loc.end = self.text_cursor;
+
+                            break;
+                        }
+
+                        if !found_comma {
+                            loc.start = before_comma;
+                            loc.end = before_comma;
+
+                            return Err(Error::expected("a comma or closing paren", loc));
+                        }
+                    }
+
+                    let callee = self.allocator.make(expr);
+                    let args = self.allocator.add_slice(&args);
+
+                    loc.end = self.text_cursor;
+                    let kind = ExprKind::Call { callee, args };
+
+                    expr = Expr { kind, loc };
+                }
+
+                // `a.field`
+                Dot => {
+                    self.adv();
+
+                    let symbol = match self.pop_kind(Word) {
+                        Some(tok) if tok.data >= Key::COUNT as u32 => tok.data,
+                        Some(_) | None => {
+                            loc.start = self.text_cursor;
+                            loc.end = self.text_cursor;
+
+                            return Err(Error::expected("a field name", loc));
+                        }
+                    };
+
+                    let value = self.allocator.make(expr);
+
+                    loc.end = self.text_cursor;
+                    let kind = ExprKind::Field { value, symbol };
+
+                    expr = Expr { kind, loc };
+                }
+
+                // `a[i]`, or `a[i..j]` for slicing
+                LBracket => {
+                    self.adv();
 
-                        expr = Expr { kind, loc };
-                        continue;
-                    }
+                    self.pop_kinds_loop(&[Skip, NewlineSkip]);
 
-                    let mut args = Pod::new();
-                    loop {
-                        let expr = self.parse_binary_op()?;
-                        args.push(expr);
+                    let mut index = self.parse_binary_op()?;
 
-                        let before_comma = self.text_cursor;
+                    self.pop_kinds_loop(&[Skip, NewlineSkip]);
 
+                    if self.pop_kind(DotDot).is_some() {
                         self.pop_kinds_loop(&[Skip, NewlineSkip]);
 
-                        let found_comma = self.pop_kind(Comma).is_some();
+                        let end = self.parse_binary_op()?;
+                        let range_loc = CodeLoc {
+                            start: index.loc.start,
+                            end: end.loc.end,
+                            file: self.file,
+                        };
 
-                        self.pop_kinds_loop(&[Skip, NewlineSkip]);
+                        let start = self.allocator.make(index);
+                        let end = self.allocator.make(end);
 
-                        if self.pop_kind(RParen).is_some() {
-                            loc.end = self.text_cursor;
+                        let kind = ExprKind::Range { start, end };
+                        index = Expr {
+                            kind,
+                            loc: range_loc,
+                        };
 
-                            break;
-                        }
+                        self.pop_kinds_loop(&[Skip, NewlineSkip]);
+                    }
 
-                        if !found_comma {
-                            loc.start = before_comma;
-                            loc.end = before_comma;
+                    if self.pop_kind(RBracket).is_none() {
+                        loc.start = self.text_cursor;
+                        loc.end = self.text_cursor;
 
-                            return Err(Error::expected("a comma or closing paren", loc));
-                        }
+                        return Err(Error::expected("a closing bracket", loc));
                     }
 
-                    let callee = self.allocator.make(expr);
-                    let args = self.allocator.add_slice(&args);
+                    let value = self.allocator.make(expr);
+                    let index = self.allocator.make(index);
 
                     loc.end = self.text_cursor;
-                    let kind = ExprKind::Call { callee, args };
+                    let kind = ExprKind::Index { value, index };
 
                     expr = Expr { kind, loc };
                 }
@@ -672,41 +1853,100 @@ impl<'a> Parser<'a> {
 
         match tok.kind {
             Word => {
+                if tok.data == Key::True as u32 || tok.data == Key::False as u32 {
+                    loc.end = self.text_cursor;
+                    let kind = ExprKind::Bool(tok.data == Key::True as u32);
+
+                    return Ok(Expr { kind, loc });
+                }
+
                 if tok.data < Key::COUNT as u32 && tok.data != Key::Type as u32 {
                     loc.end = self.text_cursor;
 
                     return Err(Error::expected("an identifer", loc));
                 }
 
+                if self.at_struct_literal() {
+                    return self.parse_struct_literal(tok.data, loc);
+                }
+
                 loc.end = self.text_cursor;
                 let kind = ExprKind::Ident { symbol: tok.data };
 
                 return Ok(Expr { kind, loc });
             }
 
+            String => {
+                loc.end = self.text_cursor;
+                let symbol = self.table.decoded[&tok.data];
+                let kind = ExprKind::String { symbol };
+
+                return Ok(Expr { kind, loc });
+            }
+
+            Char => {
+                loc.end = self.text_cursor;
+                let symbol = self.table.decoded[&tok.data];
+
+                let mut chars = self.table.names[symbol].chars();
+                let c = match (chars.next(), chars.next()) {
+                    (Some(c), None) => c,
+                    _ => {
+                        let message = "char literals should be exactly one character";
+                        return Err(Error::new(message, loc));
+                    }
+                };
+
+                let kind = ExprKind::Char(c as u32);
+
+                return Ok(Expr { kind, loc });
+            }
+
             Number => {
                 let data = self.table.names[tok.data];
 
-                let mut index = 0;
-                let mut total: u64 = 0;
+                loc.end = self.text_cursor;
+                let kind = match data.contains('.') {
+                    true => self.parse_float(data, loc)?,
+                    false => self.parse_integer(data, loc)?,
+                };
+
+                return Ok(Expr { kind, loc });
+            }
+
+            // Array literals like `[1, 2, 3]`
+            LBracket => {
+                let mut elems = Pod::new();
+                loop {
+                    self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+                    let expr = self.parse_binary_op()?;
+                    elems.push(expr);
 
-                // NOTE: just assume its an integer for now
-                for &b in data.as_bytes() {
-                    if b < b'0' || b'9' < b {
-                        loc.start = loc.start + index;
-                        loc.end = loc.start + 1;
+                    let before_comma = self.text_cursor;
 
-                        return Err(Error::expected("a digit in a number", loc));
+                    self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+                    let found_comma = self.pop_kind(Comma).is_some();
+
+                    self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+                    if self.pop_kind(RBracket).is_some() {
+                        break;
                     }
 
-                    total *= 10;
-                    total += (b - b'0') as u64;
+                    if !found_comma {
+                        loc.start = before_comma;
+                        loc.end = before_comma;
 
-                    index += 1;
+                        return Err(Error::expected("a comma or closing bracket", loc));
+                    }
                 }
 
+                let elems = self.allocator.add_slice(&elems);
+
                 loc.end = self.text_cursor;
-                let kind = ExprKind::Integer(total);
+                let kind = ExprKind::Array { elems };
 
                 return Ok(Expr { kind, loc });
             }
@@ -735,6 +1975,121 @@ impl<'a> Parser<'a> {
             }
         }
     }
+
+    // Whether the next tokens look like `{ field: value`, right after the
+    // struct name in a struct literal. Otherwise, `if a { b }` would be
+    // parsed as a struct literal. Labeled loops also look like `{ label:`,
+    // but they're always followed by `for`.
+    fn at_struct_literal(&mut self) -> bool {
+        use TokenKind::*;
+
+        let (index, text_cursor) = (self.index, self.text_cursor);
+
+        self.pop_kinds_loop(&[Skip]);
+
+        let mut found = false;
+        if self.pop_kind(LBrace).is_some() {
+            self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+            if let Some(tok) = self.pop_kind(Word) {
+                self.pop_kinds_loop(&[Skip]);
+
+                if tok.data >= Key::COUNT as u32 && self.pop_kind(Colon).is_some() {
+                    self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+                    found = !self.pop_tok(Word, Key::For as u32);
+                }
+            }
+        }
+
+        self.index = index;
+        self.text_cursor = text_cursor;
+
+        return found;
+    }
+
+    // `Point{x: 1, y: 2}`; loc starts at the struct name
+    fn parse_struct_literal(&mut self, symbol: u32, mut loc: CodeLoc) -> Result<Expr, Error> {
+        use TokenKind::*;
+
+        self.pop_kinds_loop(&[Skip]);
+        self.pop_kind(LBrace);
+
+        let mut fields = Pod::new();
+        loop {
+            self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+            let mut field_loc = CodeLoc {
+                start: self.text_cursor,
+                end: self.text_cursor,
+                file: self.file,
+            };
+
+            let field = match self.pop_kind(Word) {
+                Some(tok) if tok.data >= Key::COUNT as u32 => tok.data,
+                Some(_) | None => {
+                    field_loc.end = self.text_cursor;
+
+                    return Err(Error::expected("a field name", field_loc));
+                }
+            };
+
+            self.pop_kinds_loop(&[Skip]);
+
+            if self.pop_kind(Colon).is_none() {
+                field_loc.end = self.text_cursor;
+
+                return Err(Error::expected("a colon", field_loc));
+            }
+
+            self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+            let value = self.parse_binary_op()?;
+            field_loc.end = value.loc.end;
+
+            let value = self.allocator.make(value);
+            let kind = ExprKind::FieldValue {
+                symbol: field,
+                value,
+            };
+            fields.push(Expr {
+                kind,
+                loc: field_loc,
+            });
+
+            let before_comma = self.text_cursor;
+
+            self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+            let found_comma = self.pop_kind(Comma).is_some();
+
+            self.pop_kinds_loop(&[Skip, NewlineSkip]);
+
+            if self.pop_kind(RBrace).is_some() {
+                break;
+            }
+
+            if !found_comma {
+                loc.start = before_comma;
+                loc.end = before_comma;
+
+                return Err(Error::expected("a comma or closing brace", loc));
+            }
+        }
+
+        let fields = self.allocator.add_slice(&fields);
+
+        loc.end = self.text_cursor;
+        let kind = ExprKind::StructLiteral { symbol, fields };
+
+        return Ok(Expr { kind, loc });
+    }
+}
+
+enum ForHeader {
+    Infinite,
+    Cond(ExprId),
+    Range(u32, ExprId),
 }
 
 #[derive(Clone, Copy)]
@@ -745,9 +2100,11 @@ struct OperatorInfo {
 
     // @TODO this should be something like make_expr : (left, right) -> Result(*Expr)
     // So that we can make assignment expressions a lil nicer right off the bat
-    check_operands: Option<fn(left: &Expr, right: &Expr) -> Result<(), Error>>,
+    check_operands: Option<CheckOperands>,
 }
 
+type CheckOperands = fn(left: &Expr, right: &Expr) -> Result<(), Error>;
+
 const OPERATORS: [OperatorInfo; 256] = {
     let default_info = OperatorInfo {
         op_kind: None,
@@ -759,18 +2116,79 @@ const OPERATORS: [OperatorInfo; 256] = {
     let mut info = [default_info; 256];
     let mut idx;
 
+    // Precedence levels are the same as in C
+    idx = TokenKind::Or as usize;
+    info[idx].op_kind = Some(BinaryExprKind::BoolOr);
+    info[idx].precedence = 10;
+
+    idx = TokenKind::And as usize;
+    info[idx].op_kind = Some(BinaryExprKind::BoolAnd);
+    info[idx].precedence = 15;
+
+    idx = TokenKind::Pipe as usize;
+    info[idx].op_kind = Some(BinaryExprKind::BitOr);
+    info[idx].precedence = 20;
+
+    idx = TokenKind::Caret as usize;
+    info[idx].op_kind = Some(BinaryExprKind::BitXor);
+    info[idx].precedence = 25;
+
+    idx = TokenKind::Amp as usize;
+    info[idx].op_kind = Some(BinaryExprKind::BitAnd);
+    info[idx].precedence = 30;
+
     idx = TokenKind::Equal2 as usize;
     info[idx].op_kind = Some(BinaryExprKind::Equal);
-    info[idx].precedence = 10;
+    info[idx].precedence = 35;
+
+    idx = TokenKind::NotEqual as usize;
+    info[idx].op_kind = Some(BinaryExprKind::NotEqual);
+    info[idx].precedence = 35;
+
+    idx = TokenKind::Lt as usize;
+    info[idx].op_kind = Some(BinaryExprKind::Less);
+    info[idx].precedence = 40;
+
+    idx = TokenKind::LtEq as usize;
+    info[idx].op_kind = Some(BinaryExprKind::LessEqual);
+    info[idx].precedence = 40;
+
+    idx = TokenKind::Gt as usize;
+    info[idx].op_kind = Some(BinaryExprKind::Greater);
+    info[idx].precedence = 40;
+
+    idx = TokenKind::GtEq as usize;
+    info[idx].op_kind = Some(BinaryExprKind::GreaterEqual);
+    info[idx].precedence = 40;
+
+    idx = TokenKind::LShift as usize;
+    info[idx].op_kind = Some(BinaryExprKind::ShiftLeft);
+    info[idx].precedence = 45;
+
+    idx = TokenKind::RShift as usize;
+    info[idx].op_kind = Some(BinaryExprKind::ShiftRight);
+    info[idx].precedence = 45;
 
     idx = TokenKind::Plus as usize;
     info[idx].op_kind = Some(BinaryExprKind::Add);
     info[idx].precedence = 50;
 
+    idx = TokenKind::Dash as usize;
+    info[idx].op_kind = Some(BinaryExprKind::Subtract);
+    info[idx].precedence = 50;
+
     idx = TokenKind::Star as usize;
     info[idx].op_kind = Some(BinaryExprKind::Multiply);
     info[idx].precedence = 60;
 
+    idx = TokenKind::Div as usize;
+    info[idx].op_kind = Some(BinaryExprKind::Divide);
+    info[idx].precedence = 60;
+
+    idx = TokenKind::Mod as usize;
+    info[idx].op_kind = Some(BinaryExprKind::Modulo);
+    info[idx].precedence = 60;
+
     info
 };
 
@@ -783,7 +2201,7 @@ pub fn lex(table: &mut StringTable, file: u32, s: &str) -> Result<Pod<Token>, Er
         let start = index;
         index += 1;
 
-        'simple: loop {
+        'simple: {
             macro_rules! trailing_eq {
                 ($e1:expr, $e2:expr) => {{
                     if let Some(b'=') = bytes.get(index) {
@@ -803,25 +2221,82 @@ pub fn lex(table: &mut StringTable, file: u32, s: &str) -> Result<Pod<Token>, Er
                 b']' => TokenKind::RBracket,
                 b'{' => TokenKind::LBrace,
                 b'}' => TokenKind::RBrace,
-                b'.' => TokenKind::Dot,
+                b'.' => {
+                    if let Some(b'.') = bytes.get(index) {
+                        index += 1;
+
+                        TokenKind::DotDot
+                    } else {
+                        TokenKind::Dot
+                    }
+                }
                 b',' => TokenKind::Comma,
                 b':' => TokenKind::Colon,
                 b';' => TokenKind::Semicolon,
                 b'~' => TokenKind::Tilde,
-                b'&' => TokenKind::Amp,
-                b'^' => TokenKind::Caret,
+                b'^' => trailing_eq!(TokenKind::Caret, TokenKind::CaretEq),
+                b'&' => {
+                    if let Some(b'&') = bytes.get(index) {
+                        index += 1;
+
+                        TokenKind::And
+                    } else {
+                        trailing_eq!(TokenKind::Amp, TokenKind::AmpEq)
+                    }
+                }
+                b'|' => {
+                    if let Some(b'|') = bytes.get(index) {
+                        index += 1;
+
+                        TokenKind::Or
+                    } else {
+                        trailing_eq!(TokenKind::Pipe, TokenKind::PipeEq)
+                    }
+                }
 
                 b'!' => trailing_eq!(TokenKind::Bang, TokenKind::NotEqual),
-                b'=' => trailing_eq!(TokenKind::Equal, TokenKind::Equal2),
-                b'<' => trailing_eq!(TokenKind::Lt, TokenKind::LtEq),
-                b'>' => trailing_eq!(TokenKind::Gt, TokenKind::GtEq),
+                b'=' => {
+                    if let Some(b'>') = bytes.get(index) {
+                        index += 1;
+
+                        TokenKind::FatArrow
+                    } else {
+                        trailing_eq!(TokenKind::Equal, TokenKind::Equal2)
+                    }
+                }
+                b'<' => {
+                    if let Some(b'<') = bytes.get(index) {
+                        index += 1;
+
+                        trailing_eq!(TokenKind::LShift, TokenKind::LShiftEq)
+                    } else {
+                        trailing_eq!(TokenKind::Lt, TokenKind::LtEq)
+                    }
+                }
+                b'>' => {
+                    if let Some(b'>') = bytes.get(index) {
+                        index += 1;
+
+                        trailing_eq!(TokenKind::RShift, TokenKind::RShiftEq)
+                    } else {
+                        trailing_eq!(TokenKind::Gt, TokenKind::GtEq)
+                    }
+                }
 
                 // b'/' is handled separately because comments have more complex
                 // syntax checking
-                b'%' => TokenKind::Mod,
-                b'*' => TokenKind::Star,
-                b'+' => TokenKind::Plus,
-                b'-' => TokenKind::Dash,
+                b'%' => trailing_eq!(TokenKind::Mod, TokenKind::ModEq),
+                b'*' => trailing_eq!(TokenKind::Star, TokenKind::StarEq),
+                b'+' => trailing_eq!(TokenKind::Plus, TokenKind::PlusEq),
+                b'-' => {
+                    if let Some(b'>') = bytes.get(index) {
+                        index += 1;
+
+                        TokenKind::Arrow
+                    } else {
+                        trailing_eq!(TokenKind::Dash, TokenKind::DashEq)
+                    }
+                }
 
                 _ => break 'simple,
             };
@@ -831,10 +2306,13 @@ pub fn lex(table: &mut StringTable, file: u32, s: &str) -> Result<Pod<Token>, Er
         }
 
         if b == b'"' {
-            let end = parse_string(file, bytes, index, b'"')?;
+            let (end, decoded) = parse_string(file, bytes, index, b'"')?;
             let s = unsafe { core::str::from_utf8_unchecked(&bytes[index..(end - 1)]) };
             let data = table.add(s);
 
+            let decoded = table.add(&decoded);
+            table.decoded.insert(data, decoded);
+
             index = end;
 
             let kind = TokenKind::String;
@@ -843,10 +2321,13 @@ pub fn lex(table: &mut StringTable, file: u32, s: &str) -> Result<Pod<Token>, Er
         }
 
         if b == b'\'' {
-            let end = parse_string(file, bytes, index, b'\'')?;
+            let (end, decoded) = parse_string(file, bytes, index, b'\'')?;
             let s = unsafe { core::str::from_utf8_unchecked(&bytes[index..(end - 1)]) };
             let data = table.add(s);
 
+            let decoded = table.add(&decoded);
+            table.decoded.insert(data, decoded);
+
             index = end;
 
             let kind = TokenKind::Char;
@@ -872,7 +2353,62 @@ pub fn lex(table: &mut StringTable, file: u32, s: &str) -> Result<Pod<Token>, Er
                 continue 'outer;
             }
 
-            let kind = TokenKind::Div;
+            // Block comments nest, so that commenting out code that has block
+            // comments in it works
+            if let Some(b'*') = bytes.get(index) {
+                index += 1;
+
+                let mut depth = 1;
+                let mut has_newline = false;
+
+                while depth > 0 {
+                    let b = match bytes.get(index) {
+                        Some(&b) => b,
+                        None => {
+                            let loc = CodeLoc {
+                                file,
+                                start: start as u32,
+                                end: start as u32 + 2,
+                            };
+
+                            return Err(Error::new("unterminated block comment", loc));
+                        }
+                    };
+
+                    index += 1;
+
+                    match (b, bytes.get(index)) {
+                        (b'/', Some(b'*')) => {
+                            index += 1;
+                            depth += 1;
+                        }
+                        (b'*', Some(b'/')) => {
+                            index += 1;
+                            depth -= 1;
+                        }
+                        (b'\n', _) => has_newline = true,
+                        _ => {}
+                    }
+                }
+
+                let kind = match has_newline {
+                    true => TokenKind::NewlineSkip,
+                    false => TokenKind::Skip,
+                };
+                let data: u32 = expect((index - start).try_into());
+                tokens.push(Token { kind, data });
+                continue 'outer;
+            }
+
+            let kind = match bytes.get(index) {
+                Some(b'=') => {
+                    index += 1;
+
+                    TokenKind::DivEq
+                }
+                _ => TokenKind::Div,
+            };
+
             tokens.push(Token { kind, data: 0 });
             continue 'outer;
         }
@@ -896,7 +2432,12 @@ pub fn lex(table: &mut StringTable, file: u32, s: &str) -> Result<Pod<Token>, Er
                 false => TokenKind::Word,
 
                 true => {
-                    if let Some(b'.') = bytes.get(index).map(|b| *b) {
+                    // Only a decimal point if there's a digit after it, so that
+                    // ranges like 0..10 still lex properly
+                    let is_dot = bytes.get(index) == Some(&b'.');
+                    let is_decimal = is_dot && matches!(bytes.get(index + 1), Some(b'0'..=b'9'));
+
+                    if is_decimal {
                         index += 1;
 
                         while let Some(&b) = bytes.get(index) {
@@ -968,23 +2509,121 @@ pub fn lex(table: &mut StringTable, file: u32, s: &str) -> Result<Pod<Token>, Er
     return Ok(tokens);
 }
 
-fn parse_string(file: u32, bytes: &[u8], mut index: usize, terminator: u8) -> Result<usize, Error> {
+// Returns the index right after the terminator, along with the contents of the
+// literal with its escape sequences decoded
+fn parse_string(
+    file: u32,
+    bytes: &[u8],
+    mut index: usize,
+    terminator: u8,
+) -> Result<(usize, String), Error> {
     let start = index;
+    let mut decoded = Vec::new();
 
-    let mut escaped = false;
     while let Some(&b) = bytes.get(index) {
         index += 1;
 
-        if b == b'\\' {
-            escaped = true;
-            continue;
+        if b == terminator {
+            let decoded = unsafe { String::from_utf8_unchecked(decoded) };
+            return Ok((index, decoded));
         }
 
-        if b == b'"' && !escaped {
-            return Ok(index);
+        if b != b'\\' {
+            decoded.push(b);
+            continue;
         }
 
-        escaped = false;
+        let escape_start = index - 1;
+        let escape_loc = |end: usize| CodeLoc {
+            file,
+            start: escape_start as u32,
+            end: end as u32,
+        };
+
+        let c = match bytes.get(index) {
+            Some(&c) => c,
+            None => break,
+        };
+        index += 1;
+
+        let value = match c {
+            b'n' => b'\n',
+            b't' => b'\t',
+            b'r' => b'\r',
+            b'0' => 0,
+            b'\\' | b'"' | b'\'' => c,
+
+            // \x41 is 'A'
+            b'x' => {
+                let digits = &bytes[index..];
+                let digit_count = digits.iter().take(2).take_while(|b| b.is_ascii_hexdigit());
+                let digit_count = digit_count.count();
+
+                if digit_count < 2 {
+                    let loc = escape_loc(index + digit_count);
+                    return Err(Error::expected("two hex digits", loc));
+                }
+
+                let digits = &digits[..2];
+
+                let digits = unsafe { core::str::from_utf8_unchecked(digits) };
+                let value = expect(u8::from_str_radix(digits, 16));
+                index += 2;
+
+                if value > 0x7f {
+                    let message = "hex escapes can only go up to \\x7f";
+                    return Err(Error::new(message, escape_loc(index)));
+                }
+
+                value
+            }
+
+            // \u{1F600} is a unicode code point
+            b'u' => {
+                let digits_start = index + 1;
+                let mut digits_end = digits_start;
+                while let Some(b) = bytes.get(digits_end) {
+                    if !b.is_ascii_hexdigit() {
+                        break;
+                    }
+
+                    digits_end += 1;
+                }
+
+                let digit_count = digits_end - digits_start;
+                let has_braces =
+                    bytes.get(index) == Some(&b'{') && bytes.get(digits_end) == Some(&b'}');
+                if !has_braces || digit_count == 0 || digit_count > 6 {
+                    let message = "a unicode escape like \\u{1F600}";
+                    return Err(Error::expected(message, escape_loc(digits_end)));
+                }
+
+                index = digits_end + 1;
+
+                let digits =
+                    unsafe { core::str::from_utf8_unchecked(&bytes[digits_start..digits_end]) };
+                let value = expect(u32::from_str_radix(digits, 16));
+
+                let c = match char::from_u32(value) {
+                    Some(c) => c,
+                    None => {
+                        let message = "escape isn't a valid unicode character";
+                        return Err(Error::new(message, escape_loc(index)));
+                    }
+                };
+
+                let mut buffer = [0u8; 4];
+                decoded.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+
+                continue;
+            }
+
+            _ => {
+                return Err(Error::new("unknown escape sequence", escape_loc(index)));
+            }
+        };
+
+        decoded.push(value);
     }
 
     let loc = CodeLoc {
@@ -1000,6 +2639,11 @@ pub struct StringTable {
     allocator: BucketList,
     pub names: Pod<&'static str>,
     pub translate: HashMap<&'static str, u32>,
+
+    // String and char tokens refer to their text as written, so that tokens
+    // know their length in the source; this maps that text to the text with
+    // escapes decoded
+    pub decoded: HashMap<u32, u32>,
 }
 
 impl StringTable {
@@ -1008,6 +2652,7 @@ impl StringTable {
             allocator: BucketList::new(),
             names: Pod::new(),
             translate: HashMap::new(),
+            decoded: HashMap::new(),
         };
 
         let mut success = true;
@@ -1015,6 +2660,8 @@ impl StringTable {
         success = success && table.add("let") == Key::Let as u32;
         success = success && table.add("proc") == Key::Proc as u32;
         success = success && table.add("type") == Key::Type as u32;
+        success = success && table.add("struct") == Key::Struct as u32;
+        success = success && table.add("enum") == Key::Enum as u32;
         success = success && table.add("defer") == Key::Defer as u32;
         success = success && table.add("context") == Key::Context as u32;
 
@@ -1025,12 +2672,35 @@ impl StringTable {
         success = success && table.add("continue") == Key::Continue as u32;
         success = success && table.add("break") == Key::Break as u32;
         success = success && table.add("for") == Key::For as u32;
+        success = success && table.add("in") == Key::In as u32;
 
         success = success && table.add("spawn") == Key::Spawn as u32;
         success = success && table.add("wait") == Key::Wait as u32;
 
+        success = success && table.add("return") == Key::Return as u32;
+
+        success = success && table.add("true") == Key::True as u32;
+        success = success && table.add("false") == Key::False as u32;
+
         success = success && table.add("_") == Key::Underscore as u32;
         success = success && table.add("print") == Key::Print as u32;
+        success = success && table.add("alloc") == Key::Alloc as u32;
+        success = success && table.add("free") == Key::Free as u32;
+        success = success && table.add("len") == Key::Len as u32;
+
+        success = success && table.add("u8") == Key::U8 as u32;
+        success = success && table.add("u16") == Key::U16 as u32;
+        success = success && table.add("u32") == Key::U32 as u32;
+        success = success && table.add("u64") == Key::U64 as u32;
+        success = success && table.add("s8") == Key::S8 as u32;
+        success = success && table.add("s16") == Key::S16 as u32;
+        success = success && table.add("s32") == Key::S32 as u32;
+        success = success && table.add("s64") == Key::S64 as u32;
+        success = success && table.add("bool") == Key::Bool as u32;
+        success = success && table.add("f32") == Key::F32 as u32;
+        success = success && table.add("f64") == Key::F64 as u32;
+        success = success && table.add("char") == Key::Char as u32;
+        success = success && table.add("string") == Key::String as u32;
 
         if !success {
             panic!("Rippo");
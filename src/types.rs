@@ -80,13 +80,11 @@ pub struct FileDb {
 impl FileDb {
     #[inline]
     pub fn new() -> Self {
-        let mut new_self = Self {
+        return Self {
             buckets: BucketList::new(),
             files: Pod::new(),
             names: HashMap::new(),
         };
-
-        new_self
     }
 
     /// Add a file to the database, returning the handle that can be used to
@@ -98,7 +96,7 @@ impl FileDb {
         }
 
         let file_id = self.files.len() as u32;
-        let file = File::new(&self.buckets, file_name, &source);
+        let file = File::new(&self.buckets, file_name, source);
         self.files.push(file);
         self.names.insert((false, file.name), file_id);
 
@@ -149,7 +147,7 @@ impl<'a> files::Files<'a> for &'a FileDb {
         let file = self
             .files
             .get(file_id as usize)
-            .ok_or_else(|| files::Error::FileMissing)?;
+            .ok_or(files::Error::FileMissing)?;
 
         return Ok(file.name);
     }
@@ -158,7 +156,7 @@ impl<'a> files::Files<'a> for &'a FileDb {
         let file = self
             .files
             .get(file_id as usize)
-            .ok_or_else(|| files::Error::FileMissing)?;
+            .ok_or(files::Error::FileMissing)?;
 
         return Ok(file.source);
     }
@@ -167,7 +165,7 @@ impl<'a> files::Files<'a> for &'a FileDb {
         let file = self
             .files
             .get(file_id as usize)
-            .ok_or_else(|| files::Error::FileMissing)?;
+            .ok_or(files::Error::FileMissing)?;
 
         return match file.line_starts.binary_search(&byte_index) {
             Ok(line) => Ok(line),
@@ -179,13 +177,13 @@ impl<'a> files::Files<'a> for &'a FileDb {
         let file = self
             .files
             .get(file_id as usize)
-            .ok_or_else(|| files::Error::FileMissing)?;
+            .ok_or(files::Error::FileMissing)?;
 
         let begin = file.line_starts[line_index];
         let end = file
             .line_starts
             .get(line_index + 1)
-            .map(|i| *i)
+            .copied()
             .unwrap_or(file.source.len());
 
         return Ok(begin..end);
@@ -197,7 +195,7 @@ const PATH_SEP: u8 = b'/';
 #[cfg(target_os = "windows")]
 const PATH_SEP: u8 = b'\\';
 
-pub fn parent_if_file<'a>(path: &'a str) -> &'a str {
+pub fn parent_if_file(path: &str) -> &str {
     let bytes = path.as_bytes();
     let mut idx = bytes.len() - 1;
     while bytes[idx] != PATH_SEP {